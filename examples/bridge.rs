@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use color_eyre::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use rand::Rng;
+use ratatui::{
+    style::{Color, Style},
+    widgets::{Block, BorderType, Borders},
+    DefaultTerminal, Frame,
+};
+
+use rataudio_meter::{BarMode, Meter, MeterBridge, MeterBridgeState, MeterInput, MeterStrip};
+
+const NAMES: [&str; 32] = [
+    "Kick", "Snare", "HH", "Tom 1", "Tom 2", "Floor", "OH L", "OH R", "Room", "Bass", "Gtr 1",
+    "Gtr 2", "Keys L", "Keys R", "Organ", "Synth", "Vox", "BV 1", "BV 2", "BV 3", "Sax", "Tpt",
+    "Tbn", "Perc", "Click", "Talk", "Amb L", "Amb R", "FX 1", "FX 2", "FX 3", "FX 4",
+];
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+    let terminal = ratatui::init();
+    let result = run(terminal);
+    ratatui::restore();
+    result
+}
+
+fn run(mut terminal: DefaultTerminal) -> Result<()> {
+    let mut rng = rand::rng();
+    let mut levels = [-60.0_f32; NAMES.len()];
+    let mut state = MeterBridgeState::default();
+
+    loop {
+        for level in &mut levels {
+            *level = (*level + rng.random_range(-6.0..6.0)).clamp(-70.0, 0.0);
+        }
+
+        terminal.draw(|frame| draw(frame, &levels, &mut state))?;
+        if event::poll(Duration::from_millis(30))? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && key.code == KeyCode::Char('q') {
+                    break Ok(());
+                }
+            }
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, levels: &[f32], state: &mut MeterBridgeState) {
+    let strips = NAMES.iter().zip(levels).map(|(name, &db)| {
+        MeterStrip::new(
+            *name,
+            Meter::mono()
+                .show_labels(false)
+                .bar_mode(BarMode::Eighths)
+                .db(MeterInput::Mono(db)),
+        )
+    });
+    let bridge = MeterBridge::new(strips)
+        .block(
+            Block::default()
+                .title("Meter bridge (q to quit)")
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .db_range(-60.0, 0.0)
+        .min_strip_width(6)
+        .name_style(Style::default().fg(Color::Yellow));
+    frame.render_stateful_widget(bridge, frame.area(), state);
+}
@@ -3,13 +3,13 @@ use std::time::Duration;
 use color_eyre::Result;
 use crossterm::event::{self, Event, KeyCode, KeyEventKind};
 use ratatui::{
-    layout::{Alignment, Rect},
+    layout::{Alignment, Direction, Rect},
     style::{Color, Style},
     widgets::{Block, BorderType, Borders, Paragraph},
     DefaultTerminal, Frame,
 };
 
-use rataudio_meter::{Meter, MeterInput, MeterState};
+use rataudio_meter::{Ballistics, BarMode, Gradient, Meter, MeterInput, MeterState, Segments};
 
 fn main() -> Result<()> {
     color_eyre::install()?;
@@ -45,7 +45,11 @@ fn run(mut terminal: DefaultTerminal) -> Result<()> {
     let mut index_3 = 20;
     let mut index_4 = 30;
 
-    let mut states = [MeterState::default(), MeterState::default()];
+    let mut states = [
+        MeterState::default(),
+        MeterState::default(),
+        MeterState::with_ballistics(Ballistics::PpmType2),
+    ];
 
     loop {
         if last_time.elapsed() >= UPDATE_INTERVAL {
@@ -63,8 +67,10 @@ fn run(mut terminal: DefaultTerminal) -> Result<()> {
         }
 
         terminal.draw(|frame| draw(frame, &db_level, &mut states))?;
-        if handle_input()? == Command::Quit {
-            break Ok(());
+        match handle_input()? {
+            Command::Quit => break Ok(()),
+            Command::ResetClips => states.iter_mut().for_each(MeterState::reset_clips),
+            Command::Noop => {}
         }
     }
 }
@@ -113,26 +119,44 @@ fn draw(frame: &mut Frame, db_level: &[f32], states: &mut [MeterState]) {
         Rect::new(0, 16, 60, 5),
     );
 
-    let m3 = Paragraph::new("Meters with state - peak hold");
-    frame.render_widget(m3, Rect::new(1, 24, 40, 1));
+    let m3 = Paragraph::new("Meters with state - peak hold, clip indicator (r to reset)");
+    frame.render_widget(m3, Rect::new(1, 24, 60, 1));
 
     frame.render_stateful_widget(
-        Meter::mono().db(MeterInput::Mono(db_level[3])),
+        Meter::mono()
+            .bar_mode(BarMode::Segments(Segments::default()))
+            .db(MeterInput::Mono(db_level[3])),
         Rect::new(1, 25, 40, 3),
         &mut states[0],
     );
 
     frame.render_stateful_widget(
-        Meter::stereo().db(MeterInput::Stereo(db_level[1], db_level[2])),
+        Meter::stereo()
+            .show_clip_indicator(true)
+            .bar_mode(BarMode::Eighths)
+            .db(MeterInput::Stereo(db_level[1], db_level[2])),
         Rect::new(1, 30, 60, 5),
         &mut states[1],
     );
+
+    let m4 = Paragraph::new("Vertical - PPM, gradient");
+    frame.render_widget(m4, Rect::new(64, 16, 16, 1));
+
+    frame.render_stateful_widget(
+        Meter::stereo()
+            .direction(Direction::Vertical)
+            .gradient(Gradient::default())
+            .db(MeterInput::Stereo(db_level[1], db_level[2])),
+        Rect::new(64, 17, 16, 18),
+        &mut states[2],
+    );
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum Command {
     Noop,
     Quit,
+    ResetClips,
 }
 
 fn handle_input() -> Result<Command> {
@@ -142,6 +166,7 @@ fn handle_input() -> Result<Command> {
     match event::read()? {
         Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
             KeyCode::Char('q') => Ok(Command::Quit),
+            KeyCode::Char('r') => Ok(Command::ResetClips),
             _ => Ok(Command::Noop),
         },
         _ => Ok(Command::Noop),
@@ -0,0 +1,360 @@
+//! Level analysis of raw audio sample buffers.
+//!
+//! A [`LevelAnalyzer`] keeps a running sample peak and a windowed RMS level per channel, which
+//! can be passed straight to [`Meter::db`](crate::Meter::db).
+
+use std::time::Duration;
+
+use crate::{
+    ballistics::{Ballistics, Detector},
+    meter::MeterInput,
+    scaling::MeterScale,
+};
+
+/// Layout of the samples in a block of multichannel audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SampleLayout {
+    /// Samples of all channels alternate frame by frame: `L R L R ...`.
+    #[default]
+    Interleaved,
+    /// All samples of one channel follow each other: `L L ... R R ...`.
+    Planar,
+}
+
+/// Running per-channel sample peak, windowed RMS levels and overs.
+///
+/// The peak is held until [`LevelAnalyzer::reset_peak`] is called, typically once per UI frame,
+/// so that short transients between two frames are not lost. The RMS level is computed over the
+/// last `window` samples of each channel.
+///
+/// An over is a run of consecutive samples at or above the clip threshold, 0 dBFS by default.
+/// Only runs of at least [`LevelAnalyzer::over_length`] samples are counted, the way mastering
+/// meters flag "3-sample overs".
+///
+/// With [`LevelAnalyzer::ballistics`] the analyzer also runs the [`Ballistics`] of a VU meter or
+/// PPM on every sample, which the standards require to read short bursts correctly.
+#[derive(Debug, Clone)]
+pub struct LevelAnalyzer {
+    channels: usize,
+    peak: Vec<f32>,
+    detector: Detector,
+    detector_states: Vec<f32>,
+    readings: Vec<f32>,
+    squares: Vec<Vec<f32>>,
+    square_sums: Vec<f64>,
+    position: usize,
+    clip_threshold: f32,
+    over_length: usize,
+    runs: Vec<usize>,
+    overs: Vec<usize>,
+}
+
+impl LevelAnalyzer {
+    /// Create a new analyzer for `channels` channels with an RMS window of `window` samples.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `channels` or `window` is zero.
+    pub fn new(channels: usize, window: usize) -> Self {
+        assert!(channels > 0, "Channel count should be at least 1.");
+        assert!(window > 0, "RMS window should be at least 1 sample.");
+        Self {
+            channels,
+            peak: vec![0.0; channels],
+            detector: Detector::default(),
+            detector_states: vec![0.0; channels],
+            readings: vec![0.0; channels],
+            squares: vec![vec![0.0; window]; channels],
+            square_sums: vec![0.0; channels],
+            position: 0,
+            clip_threshold: 1.0,
+            over_length: 3,
+            runs: vec![0; channels],
+            overs: vec![0; channels],
+        }
+    }
+
+    /// Create a new analyzer with an RMS window given as a duration at `sample_rate`.
+    pub fn with_window_duration(channels: usize, sample_rate: u32, window: Duration) -> Self {
+        let window = (window.as_secs_f64() * sample_rate as f64).round() as usize;
+        Self::new(channels, window.max(1))
+    }
+
+    /// Apply `ballistics` to every sample at `sample_rate`, read with
+    /// [`LevelAnalyzer::ballistics_db`]. Defaults to [`Ballistics::Instant`], which reads the
+    /// sample peak.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn ballistics(mut self, ballistics: Ballistics, sample_rate: u32) -> Self {
+        self.detector = ballistics.detector(sample_rate);
+        self
+    }
+
+    /// Set the level in dBFS at or above which samples count towards an over. Defaults to 0 dBFS.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn clip_threshold_db(mut self, db: f32) -> Self {
+        self.clip_threshold = 10_f32.powf(db / 20.0);
+        self
+    }
+
+    /// Set the number of consecutive samples at or above the clip threshold that count as an
+    /// over. Defaults to 3.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `samples` is zero.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn over_length(mut self, samples: usize) -> Self {
+        assert!(samples > 0, "Over length should be at least 1 sample.");
+        self.over_length = samples;
+        self
+    }
+
+    /// Get the number of channels for this [`LevelAnalyzer`].
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Analyze a block of samples in the given layout.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the length of `samples` is not a multiple of the channel count.
+    pub fn process(&mut self, samples: &[f32], layout: SampleLayout) {
+        assert!(
+            samples.len().is_multiple_of(self.channels),
+            "Sample count should be a multiple of the channel count."
+        );
+        let frames = samples.len() / self.channels;
+        for frame in 0..frames {
+            for channel in 0..self.channels {
+                let sample = match layout {
+                    SampleLayout::Interleaved => samples[frame * self.channels + channel],
+                    SampleLayout::Planar => samples[channel * frames + frame],
+                };
+                self.push(channel, sample);
+            }
+            self.position = (self.position + 1) % self.squares[0].len();
+        }
+    }
+
+    /// Analyze a block of interleaved samples.
+    pub fn process_interleaved(&mut self, samples: &[f32]) {
+        self.process(samples, SampleLayout::Interleaved);
+    }
+
+    /// Analyze a block of planar samples.
+    pub fn process_planar(&mut self, samples: &[f32]) {
+        self.process(samples, SampleLayout::Planar);
+    }
+
+    fn push(&mut self, channel: usize, sample: f32) {
+        self.peak[channel] = self.peak[channel].max(sample.abs());
+
+        let state = &mut self.detector_states[channel];
+        self.detector.push(state, sample);
+        self.readings[channel] = self.readings[channel].max(self.detector.reading(*state));
+
+        if sample.abs() >= self.clip_threshold {
+            self.runs[channel] += 1;
+            if self.runs[channel] == self.over_length {
+                self.overs[channel] += 1;
+            }
+        } else {
+            self.runs[channel] = 0;
+        }
+
+        let square = sample * sample;
+        let slot = &mut self.squares[channel][self.position];
+        self.square_sums[channel] += square as f64 - *slot as f64;
+        *slot = square;
+    }
+
+    /// Sample peak amplitude per channel since the last call to [`LevelAnalyzer::reset_peak`].
+    pub fn peak(&self) -> &[f32] {
+        &self.peak
+    }
+
+    /// RMS amplitude per channel over the analysis window.
+    pub fn rms(&self) -> Vec<f32> {
+        (0..self.channels)
+            .map(|channel| self.channel_rms(channel))
+            .collect()
+    }
+
+    /// RMS amplitude of `channel` over the analysis window.
+    pub(crate) fn channel_rms(&self, channel: usize) -> f32 {
+        let window = self.squares[0].len() as f64;
+        (self.square_sums[channel].max(0.0) / window).sqrt() as f32
+    }
+
+    /// Sample peak per channel in dBFS, ready to be passed to [`Meter::db`](crate::Meter::db).
+    pub fn peak_db(&self) -> MeterInput {
+        self.peak
+            .iter()
+            .map(|&p| MeterScale::sample_to_db(p))
+            .collect()
+    }
+
+    /// RMS level per channel in dBFS, ready to be passed to [`Meter::db`](crate::Meter::db).
+    pub fn rms_db(&self) -> MeterInput {
+        self.rms()
+            .into_iter()
+            .map(MeterScale::sample_to_db)
+            .collect()
+    }
+
+    /// Highest level per channel in dBFS after the ballistics since the last call to
+    /// [`LevelAnalyzer::reset_peak`], ready to be passed to [`Meter::db`](crate::Meter::db).
+    pub fn ballistics_db(&self) -> MeterInput {
+        self.readings
+            .iter()
+            .map(|&reading| MeterScale::sample_to_db(reading))
+            .collect()
+    }
+
+    /// Number of overs per channel since the last call to [`LevelAnalyzer::reset_peak`], ready to
+    /// be passed to [`Meter::overs`](crate::Meter::overs).
+    pub fn overs(&self) -> &[usize] {
+        &self.overs
+    }
+
+    /// Reset the held sample peak and the over count of all channels.
+    ///
+    /// The level after the ballistics restarts from the current reading of each channel.
+    pub fn reset_peak(&mut self) {
+        self.peak.fill(0.0);
+        self.overs.fill(0);
+        for (reading, &state) in self.readings.iter_mut().zip(&self.detector_states) {
+            *reading = self.detector.reading(state);
+        }
+    }
+
+    /// Reset the peak and clear the RMS window.
+    pub fn reset(&mut self) {
+        self.detector_states.fill(0.0);
+        self.reset_peak();
+        for squares in &mut self.squares {
+            squares.fill(0.0);
+        }
+        self.square_sums.fill(0.0);
+        self.position = 0;
+        self.runs.fill(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    #[test]
+    fn interleaved_peak_per_channel() {
+        let mut analyzer = LevelAnalyzer::new(2, 4);
+        analyzer.process_interleaved(&[0.5, -0.25, -0.75, 0.1]);
+        assert_eq!(analyzer.peak(), &[0.75, 0.25]);
+    }
+
+    #[test]
+    fn planar_peak_per_channel() {
+        let mut analyzer = LevelAnalyzer::new(2, 4);
+        analyzer.process_planar(&[0.5, -0.75, -0.25, 0.1]);
+        assert_eq!(analyzer.peak(), &[0.75, 0.25]);
+    }
+
+    #[test]
+    fn peak_is_held_until_reset() {
+        let mut analyzer = LevelAnalyzer::new(1, 4);
+        analyzer.process_interleaved(&[0.9]);
+        analyzer.process_interleaved(&[0.1]);
+        assert_eq!(analyzer.peak(), &[0.9]);
+        analyzer.reset_peak();
+        assert_eq!(analyzer.peak(), &[0.0]);
+    }
+
+    #[test]
+    fn rms_of_full_scale_square_wave() {
+        let mut analyzer = LevelAnalyzer::new(1, 8);
+        analyzer.process_interleaved(&[1.0, -1.0, 1.0, -1.0, 1.0, -1.0, 1.0, -1.0]);
+        assert!((analyzer.rms()[0] - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn rms_of_sine_is_minus_3_db() {
+        let mut analyzer =
+            LevelAnalyzer::with_window_duration(1, 48_000, Duration::from_millis(100));
+        let sine: Vec<f32> = (0..48_000)
+            .map(|i| (i as f32 / 48.0 * std::f32::consts::TAU).sin())
+            .collect();
+        analyzer.process_interleaved(&sine);
+        let MeterInput::Channels(rms) = analyzer.rms_db() else {
+            panic!("Expected channel levels");
+        };
+        assert!((rms[0] + 3.0103).abs() < 0.01, "rms: {}", rms[0]);
+    }
+
+    #[test]
+    fn ballistics_are_applied_per_sample() {
+        let burst: Vec<f32> = (0..240)
+            .map(|i| (i as f32 / 9.6 * std::f32::consts::TAU).sin())
+            .collect();
+        let mut analyzer = LevelAnalyzer::new(1, 4).ballistics(Ballistics::PpmDin, 48_000);
+        analyzer.process_interleaved(&burst);
+        let MeterInput::Channels(level) = analyzer.ballistics_db() else {
+            panic!("Expected channel levels");
+        };
+        assert!((level[0] + 2.0).abs() < 0.1, "level: {}", level[0]);
+
+        // The reading restarts from the falling level, not from silence
+        analyzer.reset_peak();
+        analyzer.process_interleaved(&[0.0; 4800]);
+        let MeterInput::Channels(level) = analyzer.ballistics_db() else {
+            panic!("Expected channel levels");
+        };
+        assert!((-4.0..-2.0).contains(&level[0]), "level: {}", level[0]);
+    }
+
+    #[test]
+    fn default_ballistics_read_the_sample_peak() {
+        let mut analyzer = LevelAnalyzer::new(1, 4);
+        analyzer.process_interleaved(&[0.5, -0.25]);
+        assert_eq!(analyzer.ballistics_db(), analyzer.peak_db());
+    }
+
+    #[test]
+    fn rms_window_forgets_old_samples() {
+        let mut analyzer = LevelAnalyzer::new(1, 4);
+        analyzer.process_interleaved(&[1.0; 4]);
+        analyzer.process_interleaved(&[0.0; 4]);
+        assert!(analyzer.rms()[0].abs() < EPSILON);
+    }
+
+    #[test]
+    fn counts_consecutive_full_scale_samples() {
+        let mut analyzer = LevelAnalyzer::new(2, 4);
+        #[rustfmt::skip]
+        analyzer.process_planar(&[
+            1.0, 1.0, 1.0, 1.0, 0.5, -1.0, -1.0, -1.0, // three sample overs
+            1.0, 1.0, 0.5, 1.0, 1.0, 0.5, 1.0, 1.0,    // only two sample runs
+        ]);
+        assert_eq!(analyzer.overs(), &[2, 0]);
+        analyzer.reset_peak();
+        assert_eq!(analyzer.overs(), &[0, 0]);
+    }
+
+    #[test]
+    fn over_threshold_and_length() {
+        let mut analyzer = LevelAnalyzer::new(1, 4)
+            .clip_threshold_db(-1.0)
+            .over_length(1);
+        analyzer.process_interleaved(&[0.9, 0.5, 0.9, 0.8]);
+        assert_eq!(analyzer.overs(), &[2]);
+    }
+
+    #[test]
+    #[should_panic = "Sample count should be a multiple of the channel count"]
+    fn incomplete_frame() {
+        let mut analyzer = LevelAnalyzer::new(2, 4);
+        analyzer.process_interleaved(&[0.0; 3]);
+    }
+}
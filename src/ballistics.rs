@@ -0,0 +1,291 @@
+//! Standard meter ballistics.
+//!
+//! The [`Ballistics`] of a meter define how fast the displayed level rises towards (integration)
+//! and falls back from (release) the level of the signal.
+//!
+//! The integration times of the standards are defined by the reading of short tone bursts, so
+//! they can only be met on the signal itself: [`LevelAnalyzer::ballistics`] applies them to
+//! every sample of the rectified signal. A [`MeterState`] can also apply them to the levels given
+//! to the [`Meter`](crate::Meter), which smooths the display from frame to frame but integrates
+//! the levels of whole frames.
+//!
+//! [`LevelAnalyzer::ballistics`]: crate::LevelAnalyzer::ballistics
+//! [`MeterState`]: crate::MeterState
+
+use crate::constants::MIN_DB;
+
+/// Integration and release behaviour of a meter.
+///
+/// The integration times of the standards are defined by the reading of short tone bursts, so
+/// they can only be met on the signal itself: [`LevelAnalyzer::ballistics`] applies them to every
+/// sample of the rectified signal. A [`MeterState`] can also apply them to the levels given to the
+/// [`Meter`](crate::Meter), which smooths the display from frame to frame but integrates the levels
+/// of whole frames.
+///
+/// [`LevelAnalyzer::ballistics`]: crate::LevelAnalyzer::ballistics
+/// [`MeterState`]: crate::MeterState
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Ballistics {
+    /// The meter follows the input level directly.
+    #[default]
+    Instant,
+    /// VU meter: the full-wave average of the signal, reaching 99% of a steady tone in 300 ms
+    /// and falling back at the same speed. A sine reads its RMS level.
+    Vu,
+    /// IEC 60268-10 Type I PPM (DIN 45406): a 5 ms tone burst reads 2 dB below the steady tone,
+    /// 20 dB fall in 1.5 s.
+    PpmDin,
+    /// IEC 60268-10 Type I PPM (Nordic N9): a 5 ms tone burst reads 2 dB below the steady tone,
+    /// 20 dB fall in 1.7 s.
+    PpmNordic,
+    /// IEC 60268-10 Type II PPM (BBC and EBU): a 10 ms tone burst reads 4 dB below the steady
+    /// tone, 24 dB fall in 2.8 s.
+    PpmType2,
+    /// Digital peak meter: instant rise and a fall rate in dB per second.
+    DigitalPeak { fall_rate: f32 },
+}
+
+/// How the level of a [`Ballistics`] falls back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Release {
+    /// First order integration with a time constant in seconds.
+    Integrate(f32),
+    /// Linear fall in dB per second.
+    Fall(f32),
+}
+
+impl Ballistics {
+    /// Time constant of a VU meter reaching 99% of its final value in 300 ms.
+    const VU_TIME_CONSTANT: f32 = 0.3 / 4.605_17;
+
+    /// Attack time constant of a Type I PPM, at which the quasi-peak rectifier reads a 5 ms burst
+    /// of a 5 kHz tone 2 dB below the steady tone.
+    const TYPE_1_ATTACK: f32 = 0.001_276_2;
+
+    /// Attack time constant of a Type II PPM, at which the quasi-peak rectifier reads a 10 ms
+    /// burst of a 5 kHz tone 4 dB below the steady tone.
+    const TYPE_2_ATTACK: f32 = 0.004_802_5;
+
+    /// Ratio of the RMS and the full-wave average of a sine, the calibration of a VU meter.
+    const VU_GAIN: f32 = std::f32::consts::PI / (2.0 * std::f32::consts::SQRT_2);
+
+    /// Attack time constant in seconds, 0.0 for an instant rise, and release of the meter.
+    fn timing(&self) -> (f32, Release) {
+        match self {
+            Ballistics::Instant => (0.0, Release::Integrate(0.0)),
+            Ballistics::Vu => (
+                Self::VU_TIME_CONSTANT,
+                Release::Integrate(Self::VU_TIME_CONSTANT),
+            ),
+            Ballistics::PpmDin => (Self::TYPE_1_ATTACK, Release::Fall(20.0 / 1.5)),
+            Ballistics::PpmNordic => (Self::TYPE_1_ATTACK, Release::Fall(20.0 / 1.7)),
+            Ballistics::PpmType2 => (Self::TYPE_2_ATTACK, Release::Fall(24.0 / 2.8)),
+            Ballistics::DigitalPeak { fall_rate } => (0.0, Release::Fall(*fall_rate)),
+        }
+    }
+
+    /// Advance the displayed level `current_db` towards `target_db` over `dt` seconds.
+    ///
+    /// This integrates the levels as given, without rectifying a signal, see [`Ballistics`].
+    pub fn apply(&self, current_db: f32, target_db: f32, dt: f32) -> f32 {
+        let current_db = current_db.max(MIN_DB);
+        let target_db = target_db.max(MIN_DB);
+
+        let (attack, release) = self.timing();
+        if target_db > current_db {
+            return integrate(current_db, target_db, attack, dt);
+        }
+        match release {
+            Release::Integrate(tau) => integrate(current_db, target_db, tau, dt),
+            Release::Fall(fall_rate) => target_db.max(current_db - fall_rate * dt),
+        }
+    }
+
+    /// Per-sample detector of these ballistics at `sample_rate`.
+    pub(crate) fn detector(&self, sample_rate: u32) -> Detector {
+        let fs = sample_rate as f32;
+        let coefficient = |tau: f32| {
+            if tau > 0.0 {
+                1.0 - (-1.0 / (tau * fs)).exp()
+            } else {
+                1.0
+            }
+        };
+        let (attack, release) = self.timing();
+        Detector {
+            attack: coefficient(attack),
+            release: match release {
+                Release::Integrate(tau) => Release::Integrate(coefficient(tau)),
+                Release::Fall(fall_rate) => Release::Fall(10_f32.powf(-fall_rate / 20.0 / fs)),
+            },
+            gain: if *self == Ballistics::Vu {
+                Self::VU_GAIN
+            } else {
+                1.0
+            },
+        }
+    }
+}
+
+/// A [`Ballistics`] applied to every sample of the rectified signal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Detector {
+    /// Share of the distance to the input covered per sample while rising.
+    attack: f32,
+    /// Share of the distance covered per sample, or gain per sample, while falling.
+    release: Release,
+    /// Gain from the detector to the reading.
+    gain: f32,
+}
+
+impl Default for Detector {
+    fn default() -> Self {
+        Ballistics::Instant.detector(1)
+    }
+}
+
+impl Detector {
+    /// Move the detector `state` with the next `sample`.
+    pub(crate) fn push(&self, state: &mut f32, sample: f32) {
+        let input = sample.abs();
+        if input > *state {
+            *state += (input - *state) * self.attack;
+            return;
+        }
+        match self.release {
+            Release::Integrate(coefficient) => *state += (input - *state) * coefficient,
+            Release::Fall(gain) => *state = (*state * gain).max(input),
+        }
+    }
+
+    /// Amplitude read from the detector `state`.
+    pub(crate) fn reading(&self, state: f32) -> f32 {
+        state * self.gain
+    }
+}
+
+/// First order integration on the linear amplitude with time constant `tau`, reaching the target
+/// immediately for a time constant of 0.
+fn integrate(current_db: f32, target_db: f32, tau: f32, dt: f32) -> f32 {
+    if tau <= 0.0 {
+        return target_db;
+    }
+    let current = 10_f32.powf(current_db / 20.0);
+    let target = 10_f32.powf(target_db / 20.0);
+    let amplitude = current + (target - current) * (1.0 - (-dt / tau).exp());
+    (20.0 * amplitude.log10()).max(MIN_DB)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed a constant `target_db` for `duration` seconds in steps of 1 ms.
+    fn run(ballistics: Ballistics, start_db: f32, target_db: f32, duration: f32) -> f32 {
+        let mut level = start_db;
+        for _ in 0..(duration * 1000.0).round() as usize {
+            level = ballistics.apply(level, target_db, 0.001);
+        }
+        level
+    }
+
+    #[test]
+    fn instant_follows_input() {
+        assert_eq!(Ballistics::Instant.apply(-60.0, -6.0, 0.0), -6.0);
+        assert_eq!(Ballistics::Instant.apply(-6.0, -60.0, 0.0), -60.0);
+    }
+
+    #[test]
+    fn vu_reaches_99_percent_in_300_ms() {
+        let level = run(Ballistics::Vu, MIN_DB, 0.0, 0.3);
+        assert!(
+            (10_f32.powf(level / 20.0) - 0.99).abs() < 0.002,
+            "level: {level}"
+        );
+    }
+
+    /// Highest reading in dB of a full scale 5 kHz tone burst of `burst` seconds.
+    fn burst_reading(ballistics: Ballistics, sample_rate: u32, burst: f32) -> f32 {
+        let detector = ballistics.detector(sample_rate);
+        let fs = sample_rate as f32;
+        let mut state = 0.0;
+        let mut reading = 0.0_f32;
+        for i in 0..(burst * fs).round() as usize {
+            let sample = (i as f32 * 5000.0 / fs * std::f32::consts::TAU).sin();
+            detector.push(&mut state, sample);
+            reading = reading.max(detector.reading(state));
+        }
+        20.0 * reading.log10()
+    }
+
+    #[test]
+    fn ppm_type_1_burst_response() {
+        for sample_rate in [44_100, 48_000, 96_000] {
+            for (burst, expected, tolerance) in [(0.005, -2.0, 0.1), (0.010, -1.0, 0.5)] {
+                let level = burst_reading(Ballistics::PpmDin, sample_rate, burst);
+                assert!(
+                    (level - expected).abs() < tolerance,
+                    "{sample_rate} Hz, {burst} s: {level}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ppm_type_2_burst_response() {
+        for sample_rate in [44_100, 48_000, 96_000] {
+            let steady = burst_reading(Ballistics::PpmType2, sample_rate, 0.5);
+            assert!(steady > -0.5, "{sample_rate} Hz: {steady}");
+            let level = burst_reading(Ballistics::PpmType2, sample_rate, 0.010) - steady;
+            assert!((level + 4.0).abs() < 0.5, "{sample_rate} Hz: {level}");
+        }
+    }
+
+    #[test]
+    fn vu_reads_the_rms_of_a_sine() {
+        let detector = Ballistics::Vu.detector(48_000);
+        let mut state = 0.0;
+        for i in 0..48_000 {
+            let sample = (i as f32 / 48.0 * std::f32::consts::TAU).sin();
+            detector.push(&mut state, sample);
+        }
+        let level = 20.0 * detector.reading(state).log10();
+        assert!((level + 3.01).abs() < 0.1, "level: {level}");
+    }
+
+    #[test]
+    fn detector_falls_at_the_fall_rate() {
+        let detector = Ballistics::PpmDin.detector(48_000);
+        let mut state = 1.0;
+        for _ in 0..72_000 {
+            detector.push(&mut state, 0.0);
+        }
+        let level = 20.0 * detector.reading(state).log10();
+        assert!((level + 20.0).abs() < 0.05, "level: {level}");
+    }
+
+    #[test]
+    fn ppm_fall_times() {
+        let level = run(Ballistics::PpmDin, 0.0, MIN_DB, 1.5);
+        assert!((level + 20.0).abs() < 0.01, "level: {level}");
+        let level = run(Ballistics::PpmNordic, 0.0, MIN_DB, 1.7);
+        assert!((level + 20.0).abs() < 0.01, "level: {level}");
+        let level = run(Ballistics::PpmType2, 0.0, MIN_DB, 2.8);
+        assert!((level + 24.0).abs() < 0.01, "level: {level}");
+    }
+
+    #[test]
+    fn digital_peak_fall_is_frame_rate_independent() {
+        let ballistics = Ballistics::DigitalPeak { fall_rate: 20.0 };
+        let fast = run(ballistics, 0.0, MIN_DB, 0.5);
+        let slow = ballistics.apply(0.0, MIN_DB, 0.5);
+        assert!((fast - slow).abs() < 0.01);
+        assert!((slow + 10.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn digital_peak_rises_instantly() {
+        let ballistics = Ballistics::DigitalPeak { fall_rate: 20.0 };
+        assert_eq!(ballistics.apply(-40.0, -3.0, 0.001), -3.0);
+    }
+}
@@ -0,0 +1,463 @@
+//! Meter a WAV file or raw interleaved PCM from stdin in the terminal.
+//!
+//! ```text
+//! arecord -f S16_LE -r 48000 -c 2 | rataudio-meter
+//! rataudio-meter --speed 4 mix.wav
+//! rataudio-meter --report json *.wav
+//! ```
+
+mod report;
+mod source;
+
+use std::{
+    env,
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, BorderType, Borders, Paragraph},
+    DefaultTerminal, Frame,
+};
+
+use rataudio_meter::{BarMode, LevelAnalyzer, Meter, MeterState, SharedLevels};
+use report::ReportFormat;
+use source::{RawFormat, Source};
+
+const USAGE: &str = "\
+Usage: rataudio-meter [OPTIONS] [FILE]
+       rataudio-meter --report <FORMAT> [OPTIONS] [FILE]...
+
+Meter a WAV file, or WAV or raw interleaved PCM read from stdin when FILE is
+missing or '-'. With --report, measure whole files as fast as possible and
+print their sample peak, true peak, RMS, loudness, clips and DC offset.
+
+Options:
+  -f, --format <FORMAT>      Raw sample format: s16, s24, s32 or f32 [default: s16]
+  -r, --rate <RATE>          Raw sample rate in Hz [default: 48000]
+  -c, --channels <CHANNELS>  Raw channel count [default: 2]
+  -s, --speed <SPEED>        Playback speed, 0 to play as fast as possible [default: 1]
+      --report <FORMAT>      Print a report as table, json or csv instead of metering live
+  -h, --help                 Print this help
+
+Keys: q to quit, r to reset the peaks and clips.
+";
+
+/// Length of the RMS window of the meters.
+pub(crate) const RMS_WINDOW: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Options {
+    paths: Vec<PathBuf>,
+    raw: RawFormat,
+    speed: f32,
+    report: Option<ReportFormat>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            raw: RawFormat::default(),
+            speed: 1.0,
+            report: None,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("rataudio-meter: {message}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = match options.report {
+        Some(format) => run_report(&options, format),
+        None => run(options),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("rataudio-meter: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Parse the command line arguments, or return `None` when help was requested.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, String> {
+    fn value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+        let value = value.ok_or_else(|| format!("missing value for {flag}"))?;
+        value
+            .parse()
+            .map_err(|_| format!("invalid value '{value}' for {flag}"))
+    }
+
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-f" | "--format" => {
+                let format = args.next().ok_or("missing value for --format")?;
+                options.raw.format = format.parse()?;
+            }
+            "-r" | "--rate" => options.raw.sample_rate = value(&arg, args.next())?,
+            "-c" | "--channels" => options.raw.channels = value(&arg, args.next())?,
+            "-s" | "--speed" => options.speed = value(&arg, args.next())?,
+            "--report" => {
+                let format = args.next().ok_or("missing value for --report")?;
+                options.report = Some(format.parse()?);
+            }
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown option '{arg}'"))
+            }
+            _ => options.paths.push(PathBuf::from(arg)),
+        }
+    }
+    if options.report.is_none() && options.paths.len() > 1 {
+        return Err("only one file can be metered live".to_string());
+    }
+    if options.speed < 0.0 || !options.speed.is_finite() {
+        return Err("the speed should be 0 or above".to_string());
+    }
+    Ok(Some(options))
+}
+
+/// Open `path` as a source, or stdin for `-`, and return it with its display name.
+fn open(path: Option<&PathBuf>, raw: RawFormat) -> io::Result<(Source, String)> {
+    let name = match path {
+        Some(path) if path.as_os_str() != "-" => path.display().to_string(),
+        _ => "stdin".to_string(),
+    };
+    let source = match path {
+        Some(path) if path.as_os_str() != "-" => {
+            File::open(path).and_then(|file| Source::open(Box::new(file), raw))
+        }
+        _ => Source::open(Box::new(io::stdin()), raw),
+    };
+    let source = source.map_err(|err| io::Error::new(err.kind(), format!("{name}: {err}")))?;
+    Ok((source, name))
+}
+
+fn run(options: Options) -> io::Result<()> {
+    let (source, name) = open(options.paths.first(), options.raw)?;
+    let mut app = App::new(name, source.format);
+    app.player = Some(thread::spawn({
+        let levels = app.levels.clone();
+        let position = app.position.clone();
+        move || play(source, levels, position, options.speed)
+    }));
+
+    let terminal = ratatui::init();
+    let result = app.run(terminal);
+    ratatui::restore();
+    result
+}
+
+/// Measure every input of `options` and print the reports to stdout in `format`.
+fn run_report(options: &Options, format: ReportFormat) -> io::Result<()> {
+    let paths: Vec<Option<&PathBuf>> = if options.paths.is_empty() {
+        vec![None]
+    } else {
+        options.paths.iter().map(Some).collect()
+    };
+    let reports = paths
+        .into_iter()
+        .map(|path| {
+            let (mut source, name) = open(path, options.raw)?;
+            report::analyze(name, &mut source)
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let mut out = io::stdout().lock();
+    report::write_reports(&mut out, &reports, format)?;
+    out.flush()
+}
+
+/// Decode `source` in blocks of 10ms, paced at `speed` times real time, and write the levels of
+/// each block to `levels`.
+fn play(
+    mut source: Source,
+    levels: SharedLevels,
+    position: Arc<AtomicU64>,
+    speed: f32,
+) -> io::Result<()> {
+    let RawFormat {
+        sample_rate,
+        channels,
+        ..
+    } = source.format;
+    let block = (sample_rate as usize / 100).max(1);
+    let mut analyzer = LevelAnalyzer::with_window_duration(channels, sample_rate, RMS_WINDOW);
+    let mut samples = Vec::new();
+    let start = Instant::now();
+    let mut played = 0;
+    loop {
+        let frames = source.read_frames(&mut samples, block)?;
+        if frames == 0 {
+            return Ok(());
+        }
+        analyzer.process_interleaved(&samples);
+        levels.publish(&mut analyzer);
+        played += frames as u64;
+        position.store(played, Ordering::Relaxed);
+
+        if speed > 0.0 {
+            let due =
+                start + Duration::from_secs_f64(played as f64 / sample_rate as f64 / speed as f64);
+            if let Some(wait) = due.checked_duration_since(Instant::now()) {
+                thread::sleep(wait);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct App {
+    name: String,
+    format: RawFormat,
+    levels: SharedLevels,
+    position: Arc<AtomicU64>,
+    player: Option<JoinHandle<io::Result<()>>>,
+    status: String,
+    state: MeterState,
+    max_peak_db: Vec<f32>,
+}
+
+impl App {
+    fn new(name: String, format: RawFormat) -> Self {
+        Self {
+            name,
+            format,
+            levels: SharedLevels::new(format.channels),
+            position: Arc::new(AtomicU64::new(0)),
+            player: None,
+            status: "Playing".to_string(),
+            state: MeterState::default(),
+            max_peak_db: vec![f32::NEG_INFINITY; format.channels],
+        }
+    }
+
+    fn run(&mut self, mut terminal: DefaultTerminal) -> io::Result<()> {
+        loop {
+            if self.player.as_ref().is_some_and(JoinHandle::is_finished) {
+                let player = self.player.take().unwrap();
+                self.status = match player.join() {
+                    Ok(Ok(())) => "End of input".to_string(),
+                    Ok(Err(err)) => format!("Stopped: {err}"),
+                    Err(_) => "Stopped: the decoder panicked".to_string(),
+                };
+            }
+
+            terminal.draw(|frame| self.draw(frame))?;
+            match handle_input()? {
+                Command::Quit => break Ok(()),
+                Command::Reset => {
+                    self.state.reset_clips();
+                    self.state.reset_peaks();
+                    self.max_peak_db.fill(f32::NEG_INFINITY);
+                }
+                Command::Noop => {}
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let peak_db = self.levels.take_peak_db();
+        for (max, peak) in self
+            .max_peak_db
+            .iter_mut()
+            .zip(peak_db.clone().into_values())
+        {
+            *max = max.max(peak);
+        }
+
+        let channels = self.format.channels as u16;
+        let [header, meter, summary, help] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(3),
+            Constraint::Length(channels + 2),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let seconds = self.position.load(Ordering::Relaxed) as f64 / self.format.sample_rate as f64;
+        let header_text = format!(
+            " {}  {} Hz  {} ch  {}  {:02}:{:04.1}  {}",
+            self.name,
+            self.format.sample_rate,
+            self.format.channels,
+            self.format.format,
+            (seconds / 60.0) as u64,
+            seconds % 60.0,
+            self.status,
+        );
+        frame.render_widget(
+            Paragraph::new(header_text).style(Style::default().fg(Color::Yellow)),
+            header,
+        );
+
+        frame.render_stateful_widget(
+            Meter::new(self.format.channels)
+                .block(rounded_block("Levels"))
+                .show_clip_indicator(true)
+                .bar_mode(BarMode::Eighths)
+                .db(peak_db)
+                .average_db(self.levels.rms_db())
+                .overs(&self.levels.take_overs()),
+            meter,
+            &mut self.state,
+        );
+
+        let lines: Vec<Line> = (0..self.format.channels)
+            .map(|channel| {
+                let clipped = self.state.clipped.get(channel).copied().unwrap_or(false);
+                let overs = self.state.over_count.get(channel).copied().unwrap_or(0);
+                let line = Line::from(format!(
+                    " Ch {:<3} max {:>7} dBFS  overs {overs:<6} {}",
+                    channel + 1,
+                    format_db(self.max_peak_db[channel]),
+                    if clipped { "CLIP" } else { "" },
+                ));
+                if clipped {
+                    line.style(Style::default().fg(Color::Red))
+                } else {
+                    line
+                }
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(rounded_block("Clips")), summary);
+
+        frame.render_widget(Paragraph::new(" q quit  r reset peaks and clips"), help);
+    }
+}
+
+fn rounded_block(title: &str) -> Block<'_> {
+    Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+}
+
+fn format_db(db: f32) -> String {
+    if db.is_finite() {
+        format!("{db:.1}")
+    } else {
+        "-inf".to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Command {
+    Noop,
+    Quit,
+    Reset,
+}
+
+fn handle_input() -> io::Result<Command> {
+    if !event::poll(Duration::from_secs_f64(1.0 / 60.0))? {
+        return Ok(Command::Noop);
+    }
+    match event::read()? {
+        Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
+            KeyCode::Char('q') => Ok(Command::Quit),
+            KeyCode::Char('r') => Ok(Command::Reset),
+            _ => Ok(Command::Noop),
+        },
+        _ => Ok(Command::Noop),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use source::SampleFormat;
+
+    fn args(args: &[&str]) -> Result<Option<Options>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_raw_format_options() {
+        let options = args(&["-f", "f32", "--rate", "44100", "-c", "1", "-s", "0", "-"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            options,
+            Options {
+                paths: vec![PathBuf::from("-")],
+                raw: RawFormat {
+                    format: SampleFormat::F32,
+                    sample_rate: 44100,
+                    channels: 1,
+                },
+                speed: 0.0,
+                report: None,
+            }
+        );
+        assert_eq!(
+            args(&["mix.wav"]).unwrap().unwrap().paths,
+            vec![PathBuf::from("mix.wav")]
+        );
+        assert_eq!(args(&["--help"]), Ok(None));
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        assert!(args(&["-f", "u8"]).is_err());
+        assert!(args(&["--rate"]).is_err());
+        assert!(args(&["--speed", "-1"]).is_err());
+        assert!(args(&["--loud"]).is_err());
+        assert!(args(&["a.wav", "b.wav"]).is_err());
+        assert!(args(&["--report", "xml"]).is_err());
+    }
+
+    #[test]
+    fn report_takes_several_files() {
+        let options = args(&["--report", "csv", "a.wav", "b.wav"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.report, Some(ReportFormat::Csv));
+        assert_eq!(options.paths.len(), 2);
+    }
+
+    #[test]
+    fn plays_a_source_into_shared_levels() {
+        let raw = RawFormat {
+            format: SampleFormat::F32,
+            sample_rate: 100,
+            channels: 1,
+        };
+        let bytes: Vec<u8> = [1.0_f32, -1.0, 1.0, 0.25]
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+        let source = Source::open(Box::new(io::Cursor::new(bytes)), raw).unwrap();
+        let levels = SharedLevels::new(1);
+        let position = Arc::new(AtomicU64::new(0));
+        play(source, levels.clone(), position.clone(), 0.0).unwrap();
+
+        assert_eq!(position.load(Ordering::Relaxed), 4);
+        assert_eq!(levels.take_peak_db().into_values(), vec![0.0]);
+        assert_eq!(levels.take_overs(), vec![1]);
+    }
+}
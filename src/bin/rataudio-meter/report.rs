@@ -0,0 +1,411 @@
+//! Offline measurement of whole files, written as a table, JSON or CSV.
+
+use std::{
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+};
+
+use rataudio_meter::{LevelAnalyzer, LoudnessAnalyzer, TruePeakDetector};
+
+use crate::{
+    source::{RawFormat, Source},
+    RMS_WINDOW,
+};
+
+/// Number of frames decoded at a time.
+const BLOCK_FRAMES: usize = 4096;
+
+/// Output format of a report.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            _ => Err(format!(
+                "unknown report format '{s}', expected table, json or csv"
+            )),
+        }
+    }
+}
+
+/// Measurements of one channel of a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelReport {
+    pub sample_peak_db: f32,
+    pub true_peak_db: f32,
+    pub integrated_rms_db: f32,
+    pub dc_offset: f32,
+    pub clips: usize,
+}
+
+/// Measurements of a whole file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileReport {
+    pub name: String,
+    pub format: RawFormat,
+    pub frames: u64,
+    pub integrated_lufs: f32,
+    pub loudness_range_lu: f32,
+    pub channels: Vec<ChannelReport>,
+}
+
+impl FileReport {
+    /// Duration of the file in seconds.
+    fn duration(&self) -> f64 {
+        self.frames as f64 / self.format.sample_rate as f64
+    }
+}
+
+/// Measure all of `source` as fast as it can be read.
+///
+/// The sample peak, clips and true peak use the same analyzers as the live meter. The RMS level is
+/// integrated over the whole file rather than taken over the meter's RMS window, like the DC
+/// offset.
+pub fn analyze(name: String, source: &mut Source) -> io::Result<FileReport> {
+    let RawFormat {
+        sample_rate,
+        channels,
+        ..
+    } = source.format;
+    let mut levels = LevelAnalyzer::with_window_duration(channels, sample_rate, RMS_WINDOW);
+    let mut true_peak = TruePeakDetector::new(channels, sample_rate);
+    let mut loudness = LoudnessAnalyzer::new(channels, sample_rate);
+    let mut sums = vec![0.0_f64; channels];
+    let mut squares = vec![0.0_f64; channels];
+    let mut frames = 0;
+
+    let mut samples = Vec::new();
+    while source.read_frames(&mut samples, BLOCK_FRAMES)? > 0 {
+        levels.process_interleaved(&samples);
+        true_peak.process_interleaved(&samples);
+        loudness.process_interleaved(&samples);
+        for frame in samples.chunks_exact(channels) {
+            for (channel, &sample) in frame.iter().enumerate() {
+                sums[channel] += sample as f64;
+                squares[channel] += (sample as f64).powi(2);
+            }
+        }
+        frames += (samples.len() / channels) as u64;
+    }
+
+    let count = frames.max(1) as f64;
+    let sample_peaks = levels.peak_db().into_values();
+    let true_peaks = true_peak.true_peak_dbtp().into_values();
+    let channel_reports = (0..channels)
+        .map(|channel| ChannelReport {
+            sample_peak_db: sample_peaks[channel],
+            true_peak_db: true_peaks[channel],
+            integrated_rms_db: amplitude_db((squares[channel] / count).sqrt()),
+            dc_offset: (sums[channel] / count) as f32,
+            clips: levels.overs()[channel],
+        })
+        .collect();
+    Ok(FileReport {
+        name,
+        format: source.format,
+        frames,
+        integrated_lufs: loudness.integrated(),
+        loudness_range_lu: loudness.loudness_range(),
+        channels: channel_reports,
+    })
+}
+
+/// Write `reports` to `out` in `format`.
+pub fn write_reports(
+    out: &mut impl Write,
+    reports: &[FileReport],
+    format: ReportFormat,
+) -> io::Result<()> {
+    match format {
+        ReportFormat::Table => write_table(out, reports),
+        ReportFormat::Json => write_json(out, reports),
+        ReportFormat::Csv => write_csv(out, reports),
+    }
+}
+
+fn write_table(out: &mut impl Write, reports: &[FileReport]) -> io::Result<()> {
+    for (index, report) in reports.iter().enumerate() {
+        if index > 0 {
+            writeln!(out)?;
+        }
+        writeln!(
+            out,
+            "{}  {} Hz  {} ch  {}  {:.3} s  integrated {} LUFS  LRA {:.1} LU",
+            report.name,
+            report.format.sample_rate,
+            report.format.channels,
+            report.format.format,
+            report.duration(),
+            Level(report.integrated_lufs),
+            report.loudness_range_lu,
+        )?;
+        writeln!(
+            out,
+            "{:>4}  {:>9}  {:>9}  {:>13}  {:>10}  {:>6}",
+            "ch", "peak dBFS", "TP dBTP", "int. RMS dBFS", "DC offset", "clips"
+        )?;
+        for (channel, levels) in report.channels.iter().enumerate() {
+            writeln!(
+                out,
+                "{:>4}  {:>9}  {:>9}  {:>13}  {:>10.6}  {:>6}",
+                channel + 1,
+                Level(levels.sample_peak_db),
+                Level(levels.true_peak_db),
+                Level(levels.integrated_rms_db),
+                levels.dc_offset,
+                levels.clips,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn write_json(out: &mut impl Write, reports: &[FileReport]) -> io::Result<()> {
+    writeln!(out, "[")?;
+    for (index, report) in reports.iter().enumerate() {
+        writeln!(out, "  {{")?;
+        writeln!(out, "    \"file\": {},", json_string(&report.name))?;
+        writeln!(out, "    \"sample_rate\": {},", report.format.sample_rate)?;
+        writeln!(out, "    \"sample_format\": \"{}\",", report.format.format)?;
+        writeln!(out, "    \"duration\": {:.6},", report.duration())?;
+        writeln!(
+            out,
+            "    \"integrated_lufs\": {},",
+            json_number(report.integrated_lufs)
+        )?;
+        writeln!(
+            out,
+            "    \"loudness_range_lu\": {},",
+            json_number(report.loudness_range_lu)
+        )?;
+        writeln!(out, "    \"channels\": [")?;
+        for (channel, levels) in report.channels.iter().enumerate() {
+            let separator = if channel + 1 < report.channels.len() {
+                ","
+            } else {
+                ""
+            };
+            writeln!(
+                out,
+                "      {{\"channel\": {}, \"sample_peak_dbfs\": {}, \"true_peak_dbtp\": {}, \
+                 \"integrated_rms_dbfs\": {}, \"dc_offset\": {}, \"clips\": {}}}{separator}",
+                channel + 1,
+                json_number(levels.sample_peak_db),
+                json_number(levels.true_peak_db),
+                json_number(levels.integrated_rms_db),
+                json_number(levels.dc_offset),
+                levels.clips,
+            )?;
+        }
+        writeln!(out, "    ]")?;
+        let separator = if index + 1 < reports.len() { "," } else { "" };
+        writeln!(out, "  }}{separator}")?;
+    }
+    writeln!(out, "]")
+}
+
+fn write_csv(out: &mut impl Write, reports: &[FileReport]) -> io::Result<()> {
+    writeln!(
+        out,
+        "file,channel,sample_rate,duration,sample_peak_dbfs,true_peak_dbtp,integrated_rms_dbfs,\
+         dc_offset,clips,integrated_lufs,loudness_range_lu"
+    )?;
+    for report in reports {
+        for (channel, levels) in report.channels.iter().enumerate() {
+            writeln!(
+                out,
+                "{},{},{},{:.6},{},{},{},{},{},{},{}",
+                csv_field(&report.name),
+                channel + 1,
+                report.format.sample_rate,
+                report.duration(),
+                Level(levels.sample_peak_db),
+                Level(levels.true_peak_db),
+                Level(levels.integrated_rms_db),
+                levels.dc_offset,
+                levels.clips,
+                Level(report.integrated_lufs),
+                Level(report.loudness_range_lu),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Level in dBFS of a sample amplitude, or negative infinity for silence.
+fn amplitude_db(amplitude: f64) -> f32 {
+    if amplitude > 0.0 {
+        (20.0 * amplitude.log10()) as f32
+    } else {
+        f32::NEG_INFINITY
+    }
+}
+
+/// A level in dB with one decimal, or `-inf` for silence.
+struct Level(f32);
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = if self.0.is_finite() {
+            format!("{:.1}", self.0)
+        } else {
+            "-inf".to_string()
+        };
+        f.pad(&text)
+    }
+}
+
+/// A JSON number, or `null` for values JSON can not represent.
+fn json_number(value: f32) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::from('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::SampleFormat;
+
+    fn source(samples: &[f32], channels: usize) -> Source {
+        let raw = RawFormat {
+            format: SampleFormat::F32,
+            sample_rate: 48000,
+            channels,
+        };
+        let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        Source::open(Box::new(io::Cursor::new(bytes)), raw).unwrap()
+    }
+
+    fn report() -> FileReport {
+        let samples: Vec<f32> = (0..48000)
+            .flat_map(|i| {
+                let sine = 0.5 * (i as f32 * 0.1).sin();
+                [sine + 0.25, if i < 3 { 1.0 } else { 0.0 }]
+            })
+            .collect();
+        analyze("a, \"b\".wav".to_string(), &mut source(&samples, 2)).unwrap()
+    }
+
+    #[test]
+    fn measures_every_channel() {
+        let report = report();
+        assert_eq!(report.frames, 48000);
+        assert!(report.integrated_lufs.is_finite());
+
+        let [tone, clicks] = &report.channels[..] else {
+            panic!("expected two channels");
+        };
+        assert!((tone.sample_peak_db - amplitude_db(0.75)).abs() < 0.01);
+        assert!(tone.true_peak_db >= tone.sample_peak_db);
+        assert!((tone.dc_offset - 0.25).abs() < 1e-3);
+        // sqrt(0.25^2 + 0.5^2 / 2)
+        assert!((tone.integrated_rms_db - amplitude_db(0.4330)).abs() < 0.01);
+        assert_eq!(tone.clips, 0);
+        assert_eq!(clicks.sample_peak_db, 0.0);
+        assert_eq!(clicks.clips, 1);
+    }
+
+    #[test]
+    fn float_overs_are_reported_above_full_scale() {
+        let samples: Vec<f32> = (0..4800)
+            .map(|i| if i % 100 == 0 { 2.0 } else { 0.0 })
+            .collect();
+        let report = analyze("over".to_string(), &mut source(&samples, 1)).unwrap();
+        let channel = &report.channels[0];
+        assert!((channel.sample_peak_db - 6.02).abs() < 0.01);
+        assert!(
+            channel.true_peak_db > 5.5,
+            "true peak: {}",
+            channel.true_peak_db
+        );
+
+        let mut table = Vec::new();
+        write_reports(&mut table, &[report], ReportFormat::Table).unwrap();
+        assert!(String::from_utf8(table).unwrap().contains("6.0"));
+    }
+
+    #[test]
+    fn silence_is_reported_as_minus_infinity() {
+        let report = analyze("silence".to_string(), &mut source(&[0.0; 960], 1)).unwrap();
+        let mut csv = Vec::new();
+        write_reports(&mut csv, std::slice::from_ref(&report), ReportFormat::Csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("silence,1,48000,0.020000,-inf,-inf,-inf,0,0,-inf,0.0")
+        );
+
+        let mut json = Vec::new();
+        write_reports(&mut json, &[report], ReportFormat::Json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains("\"integrated_lufs\": null"));
+        assert!(json.contains("\"sample_peak_dbfs\": null"));
+    }
+
+    #[test]
+    fn escapes_file_names() {
+        let report = report();
+        let mut csv = Vec::new();
+        write_reports(&mut csv, std::slice::from_ref(&report), ReportFormat::Csv).unwrap();
+        assert!(String::from_utf8(csv)
+            .unwrap()
+            .contains("\n\"a, \"\"b\"\".wav\",1,"));
+
+        let mut json = Vec::new();
+        write_reports(&mut json, &[report], ReportFormat::Json).unwrap();
+        assert!(String::from_utf8(json)
+            .unwrap()
+            .contains("\"file\": \"a, \\\"b\\\".wav\""));
+    }
+
+    #[test]
+    fn table_has_a_row_per_channel() {
+        let mut table = Vec::new();
+        write_reports(&mut table, &[report()], ReportFormat::Table).unwrap();
+        let table = String::from_utf8(table).unwrap();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("a, \"b\".wav  48000 Hz  2 ch  f32  1.000 s"));
+        assert_eq!(
+            lines[3],
+            "   2        0.0        0.7          -42.0    0.000063       1"
+        );
+    }
+}
@@ -0,0 +1,360 @@
+//! Decoding of WAV files and raw interleaved PCM streams.
+
+use std::{
+    fmt,
+    io::{self, BufRead, BufReader, Read},
+    str::FromStr,
+};
+
+/// Size of the longest fmt chunk of a WAV file, with the WAVE_FORMAT_EXTENSIBLE fields.
+const FMT_CHUNK_SIZE: usize = 40;
+
+/// Encoding of the samples of a PCM stream. All formats are little endian.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SampleFormat {
+    #[default]
+    S16,
+    S24,
+    S32,
+    F32,
+}
+
+impl SampleFormat {
+    /// Number of bytes of one sample.
+    pub fn bytes(self) -> usize {
+        match self {
+            Self::S16 => 2,
+            Self::S24 => 3,
+            Self::S32 | Self::F32 => 4,
+        }
+    }
+
+    /// Decode one sample to an amplitude between -1.0 and 1.0.
+    fn decode(self, bytes: &[u8]) -> f32 {
+        match self {
+            Self::S16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+            Self::S24 => {
+                // Place the sample in the top bytes so the sign is kept
+                i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) as f32 / 2_147_483_648.0
+            }
+            Self::S32 => {
+                i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32
+                    / 2_147_483_648.0
+            }
+            Self::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+}
+
+impl FromStr for SampleFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "s16" => Ok(Self::S16),
+            "s24" => Ok(Self::S24),
+            "s32" => Ok(Self::S32),
+            "f32" => Ok(Self::F32),
+            _ => Err(format!(
+                "unknown sample format '{s}', expected s16, s24, s32 or f32"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SampleFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::S16 => "s16",
+            Self::S24 => "s24",
+            Self::S32 => "s32",
+            Self::F32 => "f32",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Format of raw PCM input, used when the input has no WAV header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawFormat {
+    pub format: SampleFormat,
+    pub sample_rate: u32,
+    pub channels: usize,
+}
+
+impl Default for RawFormat {
+    fn default() -> Self {
+        Self {
+            format: SampleFormat::default(),
+            sample_rate: 48000,
+            channels: 2,
+        }
+    }
+}
+
+/// A stream of interleaved samples decoded from a WAV file or raw PCM.
+pub struct Source {
+    reader: BufReader<Box<dyn Read + Send>>,
+    pub format: RawFormat,
+    /// Bytes of sample data left, `None` to read to the end of the input.
+    remaining: Option<u64>,
+    bytes: Vec<u8>,
+}
+
+impl fmt::Debug for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Source")
+            .field("format", &self.format)
+            .field("remaining", &self.remaining)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Source {
+    /// Open `reader` as a WAV stream if it starts with a RIFF header, or as raw PCM in the `raw`
+    /// format otherwise.
+    pub fn open(reader: Box<dyn Read + Send>, raw: RawFormat) -> io::Result<Self> {
+        let mut reader = BufReader::new(reader);
+        let is_wav = reader.fill_buf()?.starts_with(b"RIFF");
+        let mut source = Self {
+            reader,
+            format: raw,
+            remaining: None,
+            bytes: Vec::new(),
+        };
+        if is_wav {
+            source.read_wav_header()?;
+        }
+        if source.format.channels == 0 {
+            return Err(invalid("the channel count should be at least 1"));
+        }
+        if source.format.sample_rate < 10 {
+            return Err(invalid("the sample rate should be at least 10 Hz"));
+        }
+        Ok(source)
+    }
+
+    /// Parse the RIFF header up to the start of the sample data.
+    fn read_wav_header(&mut self) -> io::Result<()> {
+        let mut riff = [0; 12];
+        self.reader.read_exact(&mut riff)?;
+        if &riff[8..12] != b"WAVE" {
+            return Err(invalid("not a WAVE file"));
+        }
+
+        let mut format = None;
+        loop {
+            let mut header = [0; 8];
+            self.reader.read_exact(&mut header)?;
+            let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+            // Chunks are padded to an even size
+            let padded = size as u64 + (size as u64 & 1);
+            match &header[0..4] {
+                b"fmt " => {
+                    // The size comes from the file, so only the known fields are buffered
+                    let length = padded.min(FMT_CHUNK_SIZE as u64);
+                    let mut chunk = Vec::with_capacity(FMT_CHUNK_SIZE);
+                    (&mut self.reader).take(length).read_to_end(&mut chunk)?;
+                    let rest = padded - length;
+                    let skipped = io::copy(&mut (&mut self.reader).take(rest), &mut io::sink())?;
+                    if (chunk.len() as u64) < length || skipped < rest {
+                        return Err(invalid("fmt chunk is truncated"));
+                    }
+                    chunk.truncate(size as usize);
+                    format = Some(parse_fmt_chunk(&chunk)?);
+                }
+                b"data" => {
+                    self.format = format.ok_or_else(|| invalid("data chunk before fmt chunk"))?;
+                    // Streaming writers leave the size at 0 or the maximum
+                    self.remaining = (size != 0 && size != u32::MAX).then_some(size as u64);
+                    return Ok(());
+                }
+                _ => {
+                    io::copy(&mut (&mut self.reader).take(padded), &mut io::sink())?;
+                }
+            }
+        }
+    }
+
+    /// Read up to `frames` frames into `samples` as interleaved amplitudes and return the number
+    /// of frames read, 0 at the end of the input.
+    pub fn read_frames(&mut self, samples: &mut Vec<f32>, frames: usize) -> io::Result<usize> {
+        let sample_size = self.format.format.bytes();
+        let frame_size = sample_size * self.format.channels;
+        let mut length = frames * frame_size;
+        if let Some(remaining) = self.remaining {
+            length = length.min((remaining - remaining % frame_size as u64) as usize);
+        }
+
+        self.bytes.resize(length, 0);
+        let mut filled = 0;
+        while filled < length {
+            match self.reader.read(&mut self.bytes[filled..]) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        // Drop a trailing partial frame
+        let filled = filled - filled % frame_size;
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= filled as u64;
+        }
+
+        samples.clear();
+        samples.extend(
+            self.bytes[..filled]
+                .chunks_exact(sample_size)
+                .map(|bytes| self.format.format.decode(bytes)),
+        );
+        Ok(filled / frame_size)
+    }
+}
+
+/// Parse the contents of a WAV `fmt ` chunk.
+fn parse_fmt_chunk(chunk: &[u8]) -> io::Result<RawFormat> {
+    if chunk.len() < 16 {
+        return Err(invalid("fmt chunk is too short"));
+    }
+    let u16_at = |at: usize| u16::from_le_bytes([chunk[at], chunk[at + 1]]);
+    let mut tag = u16_at(0);
+    let channels = u16_at(2) as usize;
+    let sample_rate = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+    let bits = u16_at(14);
+    // WAVE_FORMAT_EXTENSIBLE keeps the format tag at the start of the sub-format GUID
+    if tag == 0xFFFE && chunk.len() >= 26 {
+        tag = u16_at(24);
+    }
+
+    let format = match (tag, bits) {
+        (1, 16) => SampleFormat::S16,
+        (1, 24) => SampleFormat::S24,
+        (1, 32) => SampleFormat::S32,
+        (3, 32) => SampleFormat::F32,
+        _ => {
+            return Err(invalid(&format!(
+                "unsupported WAV encoding (format {tag}, {bits} bits)"
+            )))
+        }
+    };
+    Ok(RawFormat {
+        format,
+        sample_rate,
+        channels,
+    })
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a WAV file with a `LIST` chunk before the sample data.
+    fn wav(tag: u16, channels: u16, bits: u16, data: &[u8]) -> Vec<u8> {
+        let mut bytes = b"RIFF\0\0\0\0WAVE".to_vec();
+        bytes.extend(b"fmt \x10\0\0\0");
+        bytes.extend(tag.to_le_bytes());
+        bytes.extend(channels.to_le_bytes());
+        bytes.extend(44100_u32.to_le_bytes());
+        bytes.extend((44100 * (channels * bits / 8) as u32).to_le_bytes());
+        bytes.extend((channels * bits / 8).to_le_bytes());
+        bytes.extend(bits.to_le_bytes());
+        bytes.extend(b"LIST\x03\0\0\0abc\0");
+        bytes.extend(b"data");
+        bytes.extend((data.len() as u32).to_le_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    fn open(bytes: Vec<u8>, raw: RawFormat) -> Source {
+        Source::open(Box::new(io::Cursor::new(bytes)), raw).unwrap()
+    }
+
+    #[test]
+    fn decodes_all_sample_formats() {
+        assert_eq!(SampleFormat::S16.decode(&[0x00, 0x80]), -1.0);
+        assert_eq!(SampleFormat::S16.decode(&[0x00, 0x40]), 0.5);
+        assert_eq!(SampleFormat::S24.decode(&[0x00, 0x00, 0xC0]), -0.5);
+        assert_eq!(SampleFormat::S32.decode(&[0, 0, 0, 0x40]), 0.5);
+        assert_eq!(SampleFormat::F32.decode(&0.25_f32.to_le_bytes()), 0.25);
+    }
+
+    #[test]
+    fn reads_the_format_from_the_wav_header() {
+        let data = [0x00, 0x40, 0x00, 0xC0, 0xFF, 0x7F, 0x00, 0x00];
+        let mut source = open(wav(1, 2, 16, &data), RawFormat::default());
+        assert_eq!(
+            source.format,
+            RawFormat {
+                format: SampleFormat::S16,
+                sample_rate: 44100,
+                channels: 2,
+            }
+        );
+
+        let mut samples = Vec::new();
+        assert_eq!(source.read_frames(&mut samples, 10).unwrap(), 2);
+        assert_eq!(samples[..2], [0.5, -0.5]);
+        assert_eq!(source.read_frames(&mut samples, 10).unwrap(), 0);
+    }
+
+    #[test]
+    fn reads_float_wav() {
+        let data: Vec<u8> = [0.5_f32, -0.75]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        let mut source = open(wav(3, 1, 32, &data), RawFormat::default());
+        let mut samples = Vec::new();
+        assert_eq!(source.read_frames(&mut samples, 1).unwrap(), 1);
+        assert_eq!(samples, [0.5]);
+        assert_eq!(source.read_frames(&mut samples, 1).unwrap(), 1);
+        assert_eq!(samples, [-0.75]);
+    }
+
+    #[test]
+    fn reads_raw_pcm_and_drops_partial_frames() {
+        let raw = RawFormat {
+            format: SampleFormat::S24,
+            sample_rate: 48000,
+            channels: 2,
+        };
+        let mut source = open(vec![0, 0, 0x40, 0, 0, 0xC0, 0, 0, 0x40, 0], raw);
+        assert_eq!(source.format, raw);
+        let mut samples = Vec::new();
+        assert_eq!(source.read_frames(&mut samples, 4).unwrap(), 1);
+        assert_eq!(samples, [0.5, -0.5]);
+        assert_eq!(source.read_frames(&mut samples, 4).unwrap(), 0);
+    }
+
+    #[test]
+    fn skips_fmt_chunk_extensions() {
+        let mut bytes = wav(1, 1, 16, &[0x00, 0x40]);
+        // Announce a 4 GB fmt chunk that ends before it reaches the data
+        bytes[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = Source::open(Box::new(io::Cursor::new(bytes)), RawFormat::default());
+        assert_eq!(err.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // A long fmt chunk is read up to the known fields and the rest is skipped
+        let mut bytes = wav(1, 1, 16, &[0x00, 0x40]);
+        bytes[16..20].copy_from_slice(&66_u32.to_le_bytes());
+        let extension = [0; 50];
+        bytes.splice(36..36, extension);
+        let mut source = open(bytes, RawFormat::default());
+        assert_eq!(source.format.format, SampleFormat::S16);
+        let mut samples = Vec::new();
+        assert_eq!(source.read_frames(&mut samples, 4).unwrap(), 1);
+        assert_eq!(samples, [0.5]);
+    }
+
+    #[test]
+    fn rejects_unsupported_wav() {
+        let bytes = wav(1, 1, 8, &[0x80]);
+        let err = Source::open(Box::new(io::Cursor::new(bytes)), RawFormat::default());
+        assert_eq!(err.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
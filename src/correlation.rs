@@ -0,0 +1,155 @@
+//! Phase correlation measurement of stereo signals.
+//!
+//! A [`CorrelationAnalyzer`] computes the normalised correlation coefficient between the left and
+//! right channel over a sliding window. The result can be displayed with the
+//! [`CorrelationMeter`](crate::CorrelationMeter) widget.
+
+use std::time::Duration;
+
+use crate::analysis::SampleLayout;
+
+/// Windowed correlation coefficient between the two channels of a stereo signal.
+///
+/// The coefficient is +1 for identical channels (mono), 0 for uncorrelated channels and -1 for
+/// channels with opposite polarity. Silence reads 0.
+#[derive(Debug, Clone)]
+pub struct CorrelationAnalyzer {
+    products: Vec<[f32; 3]>,
+    sums: [f64; 3],
+    position: usize,
+}
+
+impl CorrelationAnalyzer {
+    /// Create a new analyzer with a window of `window` frames.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `window` is zero.
+    pub fn new(window: usize) -> Self {
+        assert!(window > 0, "Correlation window should be at least 1 frame.");
+        Self {
+            products: vec![[0.0; 3]; window],
+            sums: [0.0; 3],
+            position: 0,
+        }
+    }
+
+    /// Create a new analyzer with a window given as a duration at `sample_rate`.
+    pub fn with_window_duration(sample_rate: u32, window: Duration) -> Self {
+        let window = (window.as_secs_f64() * sample_rate as f64).round() as usize;
+        Self::new(window.max(1))
+    }
+
+    /// Analyze a block of stereo samples in the given layout.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the length of `samples` is odd.
+    pub fn process(&mut self, samples: &[f32], layout: SampleLayout) {
+        assert!(
+            samples.len().is_multiple_of(2),
+            "Sample count should be a multiple of the channel count."
+        );
+        let frames = samples.len() / 2;
+        for frame in 0..frames {
+            let (left, right) = match layout {
+                SampleLayout::Interleaved => (samples[2 * frame], samples[2 * frame + 1]),
+                SampleLayout::Planar => (samples[frame], samples[frames + frame]),
+            };
+            self.push(left, right);
+        }
+    }
+
+    /// Analyze a block of interleaved stereo samples.
+    pub fn process_interleaved(&mut self, samples: &[f32]) {
+        self.process(samples, SampleLayout::Interleaved);
+    }
+
+    /// Analyze a block of planar stereo samples.
+    pub fn process_planar(&mut self, samples: &[f32]) {
+        self.process(samples, SampleLayout::Planar);
+    }
+
+    fn push(&mut self, left: f32, right: f32) {
+        let products = [left * right, left * left, right * right];
+        let slot = &mut self.products[self.position];
+        for ((sum, new), old) in self.sums.iter_mut().zip(products).zip(*slot) {
+            *sum += new as f64 - old as f64;
+        }
+        *slot = products;
+        self.position = (self.position + 1) % self.products.len();
+    }
+
+    /// Correlation coefficient between -1.0 and +1.0.
+    pub fn correlation(&self) -> f32 {
+        let [lr, ll, rr] = self.sums;
+        let power = (ll.max(0.0) * rr.max(0.0)).sqrt();
+        if power < 1e-12 {
+            return 0.0;
+        }
+        (lr / power).clamp(-1.0, 1.0) as f32
+    }
+
+    /// Clear the correlation window.
+    pub fn reset(&mut self) {
+        self.products.fill([0.0; 3]);
+        self.sums = [0.0; 3];
+        self.position = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stereo(left: impl Fn(f32) -> f32, right: impl Fn(f32) -> f32) -> Vec<f32> {
+        (0..4800)
+            .flat_map(|i| {
+                let t = i as f32 / 48_000.0;
+                [left(t), right(t)]
+            })
+            .collect()
+    }
+
+    fn sine(frequency: f32) -> impl Fn(f32) -> f32 {
+        move |t| (t * frequency * std::f32::consts::TAU).sin()
+    }
+
+    #[test]
+    fn identical_channels() {
+        let mut analyzer = CorrelationAnalyzer::new(4800);
+        analyzer.process_interleaved(&stereo(sine(440.0), sine(440.0)));
+        assert!((analyzer.correlation() - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn opposite_polarity() {
+        let mut analyzer = CorrelationAnalyzer::new(4800);
+        analyzer.process_interleaved(&stereo(sine(440.0), |t| -sine(440.0)(t)));
+        assert!((analyzer.correlation() + 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn quadrature_is_uncorrelated() {
+        let mut analyzer = CorrelationAnalyzer::new(4800);
+        analyzer.process_interleaved(&stereo(sine(500.0), |t| {
+            (t * 500.0 * std::f32::consts::TAU).cos()
+        }));
+        assert!(analyzer.correlation().abs() < 1e-2);
+    }
+
+    #[test]
+    fn silence_reads_zero() {
+        let mut analyzer = CorrelationAnalyzer::new(16);
+        analyzer.process_planar(&[0.0; 32]);
+        assert_eq!(analyzer.correlation(), 0.0);
+    }
+
+    #[test]
+    fn window_forgets_old_frames() {
+        let mut analyzer = CorrelationAnalyzer::new(4800);
+        analyzer.process_interleaved(&stereo(sine(440.0), |t| -sine(440.0)(t)));
+        analyzer.process_interleaved(&stereo(sine(440.0), sine(440.0)));
+        assert!((analyzer.correlation() - 1.0).abs() < 1e-3);
+    }
+}
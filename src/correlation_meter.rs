@@ -0,0 +1,313 @@
+//! The [`CorrelationMeter`] widget is used to display the phase correlation of a stereo signal.
+
+use std::time::{Duration, Instant};
+
+use ratatui::{
+    layout::Direction,
+    prelude::{symbols, BlockExt, Buffer, Color, Rect, Style, Widget},
+    widgets::{Block, Paragraph, StatefulWidget},
+};
+
+use crate::{
+    rendering::{render_scale_label, Bar, MeterAreas},
+    state::{Clock, Clocked},
+};
+
+/// State of the [`CorrelationMeter`] widget
+///
+/// When the meter is rendered as a stateful widget, it will mark the most negative correlation
+/// for a certain amount of time.
+///
+/// The state consists of:
+/// - [`min_hold`](CorrelationState::min_hold): the most negative correlation to be displayed
+/// - [`min_hold_time`](CorrelationState::min_hold_time): the amount of time the most negative
+///   correlation will be held
+/// - [`last_min_time`](CorrelationState::last_min_time): the time when the most negative
+///   correlation was last updated
+/// - [`clock`](CorrelationState::clock): the source of time and the current time of the state
+#[derive(Debug, Clone)]
+pub struct CorrelationState {
+    pub min_hold: f32,
+    pub last_min_time: Instant,
+    pub min_hold_time: Duration,
+    pub clock: Clocked,
+}
+
+impl Default for CorrelationState {
+    fn default() -> Self {
+        let clock = Clocked::default();
+        Self {
+            min_hold: 1.0,
+            last_min_time: clock.now,
+            min_hold_time: Duration::from_secs(3),
+            clock,
+        }
+    }
+}
+
+impl CorrelationState {
+    /// Create a new [`CorrelationState`] with the given [`Clock`].
+    pub fn with_clock(clock: Clock) -> Self {
+        let clock = Clocked::new(clock);
+        Self {
+            last_min_time: clock.now,
+            clock,
+            ..Default::default()
+        }
+    }
+}
+
+/// A widget to display a phase correlation meter.
+///
+/// A `CorrelationMeter` renders a bipolar bar from -1 to +1 centred at 0, filled from the centre
+/// towards the value given to [`CorrelationMeter::new`]. Negative correlation, which indicates
+/// mono compatibility problems, is drawn in red.
+///
+/// [`CorrelationMeter`] is also a [`StatefulWidget`], which can be used with [`CorrelationState`]
+/// to hold the most negative correlation for a certain amount of time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorrelationMeter<'a> {
+    pub(crate) block: Option<Block<'a>>,
+    pub(crate) correlation: f32,
+    pub(crate) show_labels: bool,
+    pub(crate) show_scale: bool,
+    pub(crate) direction: Direction,
+}
+
+impl<'a> CorrelationMeter<'a> {
+    /// Create a new [`CorrelationMeter`] widget displaying `correlation`.
+    ///
+    /// Values outside -1.0 to +1.0 are clamped.
+    pub fn new(correlation: f32) -> Self {
+        Self {
+            block: None,
+            correlation: correlation.clamp(-1.0, 1.0),
+            show_labels: true,
+            show_scale: true,
+            direction: Direction::Horizontal,
+        }
+    }
+
+    /// Surrounds the `CorrelationMeter` with a [`Block`].
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    /// Show or hide the correlation label for the [`CorrelationMeter`].
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn show_labels(mut self, show: bool) -> Self {
+        self.show_labels = show;
+        self
+    }
+
+    /// Show or hide the scale of the [`CorrelationMeter`].
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn show_scale(mut self, show: bool) -> Self {
+        self.show_scale = show;
+        self
+    }
+
+    /// Set the direction of the [`CorrelationMeter`]. -1 is at the left or bottom end.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    fn get_color(correlation: f32) -> Color {
+        if correlation < 0.0 {
+            Color::Red
+        } else {
+            Color::Green
+        }
+    }
+}
+
+/// Position of `correlation` along a bar of `len` cells.
+fn position(correlation: f32, len: u16) -> u16 {
+    ((correlation + 1.0) / 2.0 * len.saturating_sub(1) as f32).round() as u16
+}
+
+impl Widget for CorrelationMeter<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Widget::render(&self, area, buf);
+    }
+}
+
+impl Widget for &CorrelationMeter<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut state = CorrelationState::default();
+        StatefulWidget::render(self, area, buf, &mut state);
+    }
+}
+
+impl StatefulWidget for CorrelationMeter<'_> {
+    type State = CorrelationState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        StatefulWidget::render(&self, area, buf, state);
+    }
+}
+
+impl StatefulWidget for &CorrelationMeter<'_> {
+    type State = CorrelationState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        if let Some(block) = self.block.as_ref() {
+            block.render(area, buf);
+        }
+
+        let meter_area = self.block.inner_if_some(area);
+        if meter_area.is_empty() {
+            return;
+        }
+
+        let areas = MeterAreas::new(
+            meter_area,
+            1,
+            self.show_labels,
+            self.show_scale,
+            self.direction,
+        );
+        let bar = Bar {
+            area: areas.meters[0],
+            direction: self.direction,
+        };
+        let len = bar.len();
+
+        // --- METER BAR ---
+        let center = position(0.0, len);
+        let value = position(self.correlation, len);
+        for pos in center.min(value)..=center.max(value) {
+            let correlation = pos as f32 / len.saturating_sub(1).max(1) as f32 * 2.0 - 1.0;
+            bar.set(
+                buf,
+                pos,
+                symbols::block::SEVEN_EIGHTHS,
+                CorrelationMeter::get_color(correlation),
+            );
+        }
+
+        // --- MINIMUM HOLD ---
+        let now = state.clock.read();
+        if self.correlation < state.min_hold
+            || now.saturating_duration_since(state.last_min_time) > state.min_hold_time
+        {
+            state.min_hold = self.correlation;
+            state.last_min_time = now;
+        }
+
+        // --- MINIMUM MARKER ---
+        if state.min_hold < 0.0 {
+            bar.set(
+                buf,
+                position(state.min_hold, len),
+                symbols::block::SEVEN_EIGHTHS,
+                CorrelationMeter::get_color(state.min_hold),
+            );
+        }
+
+        // --- CORRELATION LABEL ---
+        if let Some(label_areas) = areas.labels.as_ref() {
+            Paragraph::new(format!("{:+.2}", self.correlation)).render(label_areas[0], buf);
+        }
+
+        // --- SCALE LABELS ---
+        if let Some(scale_area) = areas.scale {
+            render_scale_label(
+                self.direction,
+                "-1",
+                0.0,
+                scale_area,
+                buf,
+                Some(1),
+                Style::default(),
+            );
+            render_scale_label(
+                self.direction,
+                "0",
+                0.5,
+                scale_area,
+                buf,
+                None,
+                Style::default(),
+            );
+            render_scale_label(
+                self.direction,
+                "+1",
+                1.0,
+                scale_area,
+                buf,
+                Some(-1),
+                Style::default(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(meter: CorrelationMeter, state: &mut CorrelationState) -> Buffer {
+        let area = Rect::new(0, 0, 21, 3);
+        let mut buf = Buffer::empty(area);
+        StatefulWidget::render(meter, area, &mut buf, state);
+        buf
+    }
+
+    #[test]
+    fn positive_correlation_fills_right_of_center() {
+        let buf = render(CorrelationMeter::new(0.5), &mut CorrelationState::default());
+        assert_eq!(buf[(9, 1)].symbol(), " ");
+        assert_eq!(buf[(10, 1)].fg, Color::Green);
+        assert_eq!(buf[(15, 1)].fg, Color::Green);
+        assert_eq!(buf[(16, 1)].symbol(), " ");
+    }
+
+    #[test]
+    fn negative_correlation_is_red() {
+        let buf = render(
+            CorrelationMeter::new(-1.0),
+            &mut CorrelationState::default(),
+        );
+        assert_eq!(buf[(0, 1)].fg, Color::Red);
+        assert_eq!(buf[(9, 1)].fg, Color::Red);
+        assert_eq!(buf[(11, 1)].symbol(), " ");
+    }
+
+    #[test]
+    fn most_negative_value_is_held() {
+        let mut state = CorrelationState::default();
+        render(CorrelationMeter::new(-0.8), &mut state);
+        let buf = render(CorrelationMeter::new(0.9), &mut state);
+        assert_eq!(state.min_hold, -0.8);
+        assert_eq!(buf[(2, 1)].fg, Color::Red);
+        assert_eq!(buf[(3, 1)].symbol(), " ");
+    }
+
+    #[test]
+    fn held_minimum_follows_the_manual_clock() {
+        let mut state = CorrelationState::with_clock(Clock::Manual);
+        render(CorrelationMeter::new(-0.8), &mut state);
+        state.clock.update(Duration::from_secs(3));
+        render(CorrelationMeter::new(0.9), &mut state);
+        assert_eq!(state.min_hold, -0.8);
+        state.clock.update(Duration::from_millis(1));
+        render(CorrelationMeter::new(0.9), &mut state);
+        assert_eq!(state.min_hold, 0.9);
+    }
+
+    #[test]
+    fn label_and_scale() {
+        let buf = render(
+            CorrelationMeter::new(0.25),
+            &mut CorrelationState::default(),
+        );
+        let line = |y: u16| -> String { (0..21).map(|x| buf[(x, y)].symbol()).collect() };
+        assert_eq!(line(0).trim_end(), "+0.25");
+        assert_eq!(line(2), "-1        0        +1");
+    }
+}
@@ -0,0 +1,378 @@
+//! The [`Goniometer`] widget is used to display a stereo vectorscope.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    layout::{Constraint, Flex, Layout},
+    prelude::{BlockExt, Buffer, Color, Rect, Widget},
+    symbols::Marker,
+    widgets::{
+        canvas::{Canvas, Line, Points},
+        Block, StatefulWidget,
+    },
+};
+
+use crate::{
+    analysis::SampleLayout,
+    state::{Clock, Clocked},
+};
+
+/// Number of shades older points fade through.
+const FADE_STEPS: usize = 4;
+
+/// How the stereo sample cloud is plotted by the [`Goniometer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GoniometerMode {
+    /// Rotated by 45 degrees: Mid (L+R) up and Side (L-R) sideways. A mono signal is a vertical
+    /// line and a left-only signal a line towards the upper left.
+    #[default]
+    Goniometer,
+    /// Not rotated: left channel on the horizontal and right channel on the vertical axis.
+    Lissajous,
+}
+
+/// State of the [`Goniometer`] widget
+///
+/// The state holds the recent stereo sample pairs pushed by the audio side, together with the time
+/// they were pushed, so older points can be faded out.
+///
+/// The state consists of:
+/// - [`fade_time`](GoniometerState::fade_time): the amount of time a point stays visible
+/// - [`max_points`](GoniometerState::max_points): the maximum number of points kept, oldest
+///   points are dropped first
+/// - [`clock`](GoniometerState::clock): the source of time and the current time of the state
+#[derive(Debug, Clone)]
+pub struct GoniometerState {
+    pub fade_time: Duration,
+    pub max_points: usize,
+    pub clock: Clocked,
+    blocks: VecDeque<(Instant, Vec<(f32, f32)>)>,
+    points: usize,
+}
+
+impl Default for GoniometerState {
+    fn default() -> Self {
+        Self {
+            fade_time: Duration::from_millis(500),
+            max_points: 8192,
+            clock: Clocked::default(),
+            blocks: VecDeque::new(),
+            points: 0,
+        }
+    }
+}
+
+impl GoniometerState {
+    /// Create a new [`GoniometerState`] with the given [`Clock`].
+    ///
+    /// Blocks pushed to the state are stamped with the current time of the clock.
+    pub fn with_clock(clock: Clock) -> Self {
+        Self {
+            clock: Clocked::new(clock),
+            ..Default::default()
+        }
+    }
+
+    /// Add a block of stereo samples in the given layout.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the length of `samples` is odd.
+    pub fn push(&mut self, samples: &[f32], layout: SampleLayout) {
+        assert!(
+            samples.len().is_multiple_of(2),
+            "Sample count should be a multiple of the channel count."
+        );
+        let frames = samples.len() / 2;
+        let block: Vec<(f32, f32)> = (0..frames)
+            .map(|frame| match layout {
+                SampleLayout::Interleaved => (samples[2 * frame], samples[2 * frame + 1]),
+                SampleLayout::Planar => (samples[frame], samples[frames + frame]),
+            })
+            .collect();
+
+        let now = self.clock.read();
+        self.points += block.len();
+        self.blocks.push_back((now, block));
+        self.prune(now);
+    }
+
+    /// Add a block of interleaved stereo samples.
+    pub fn push_interleaved(&mut self, samples: &[f32]) {
+        self.push(samples, SampleLayout::Interleaved);
+    }
+
+    /// Add a block of planar stereo samples.
+    pub fn push_planar(&mut self, samples: &[f32]) {
+        self.push(samples, SampleLayout::Planar);
+    }
+
+    /// Remove all points.
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+        self.points = 0;
+    }
+
+    /// Drop blocks that faded out at `now`, then the oldest points that exceed the maximum
+    /// number of points.
+    fn prune(&mut self, now: Instant) {
+        while let Some((time, block)) = self.blocks.front_mut() {
+            let excess = self.points.saturating_sub(self.max_points);
+            let faded = now.saturating_duration_since(*time) > self.fade_time;
+            if !faded && excess < block.len() {
+                block.drain(..excess);
+                self.points -= excess;
+                break;
+            }
+            self.points -= block.len();
+            self.blocks.pop_front();
+        }
+    }
+}
+
+/// A widget to display a goniometer, also known as a stereo vectorscope.
+///
+/// A `Goniometer` plots the stereo sample pairs held by a [`GoniometerState`] in a square region
+/// of the area it is rendered in, using braille or half-block characters for sub-cell resolution.
+/// Older points fade out over [`GoniometerState::fade_time`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Goniometer<'a> {
+    pub(crate) block: Option<Block<'a>>,
+    pub(crate) mode: GoniometerMode,
+    pub(crate) marker: Marker,
+    pub(crate) color: Color,
+    pub(crate) show_axes: bool,
+}
+
+impl Default for Goniometer<'_> {
+    fn default() -> Self {
+        Self {
+            block: None,
+            mode: GoniometerMode::default(),
+            marker: Marker::Braille,
+            color: Color::Green,
+            show_axes: true,
+        }
+    }
+}
+
+impl<'a> Goniometer<'a> {
+    /// Surrounds the `Goniometer` with a [`Block`].
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    /// Set how the sample cloud is plotted.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn mode(mut self, mode: GoniometerMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set the marker used to plot points, e.g. [`Marker::Braille`] or [`Marker::HalfBlock`].
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn marker(mut self, marker: Marker) -> Self {
+        self.marker = marker;
+        self
+    }
+
+    /// Set the color of the newest points.
+    ///
+    /// RGB colors fade towards black. Other colors fade to [`Color::DarkGray`].
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Show or hide the L, R, M and S axes.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn show_axes(mut self, show: bool) -> Self {
+        self.show_axes = show;
+        self
+    }
+
+    /// Map a sample pair to plot coordinates between -1.0 and 1.0.
+    fn transform(&self, (left, right): (f32, f32)) -> (f64, f64) {
+        let (left, right) = (left as f64, right as f64);
+        match self.mode {
+            GoniometerMode::Goniometer => (
+                (right - left) * std::f64::consts::FRAC_1_SQRT_2,
+                (left + right) * std::f64::consts::FRAC_1_SQRT_2,
+            ),
+            GoniometerMode::Lissajous => (left, right),
+        }
+    }
+
+    /// Color of points in fade step `step`, where 0 is the newest.
+    fn shade(&self, step: usize) -> Color {
+        let brightness = 1.0 - step as f32 / FADE_STEPS as f32;
+        match self.color {
+            Color::Rgb(r, g, b) => Color::Rgb(
+                (r as f32 * brightness) as u8,
+                (g as f32 * brightness) as u8,
+                (b as f32 * brightness) as u8,
+            ),
+            color if step < FADE_STEPS / 2 => color,
+            _ => Color::DarkGray,
+        }
+    }
+}
+
+/// The largest area with a 2:1 width to height ratio centred in `area`, which is square on
+/// terminals with cells twice as high as wide.
+fn square_area(area: Rect) -> Rect {
+    let height = area.height.min(area.width / 2).max(1);
+    let width = (height * 2).min(area.width);
+    let [area] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(area);
+    area
+}
+
+impl StatefulWidget for Goniometer<'_> {
+    type State = GoniometerState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        StatefulWidget::render(&self, area, buf, state);
+    }
+}
+
+impl StatefulWidget for &Goniometer<'_> {
+    type State = GoniometerState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        if let Some(block) = self.block.as_ref() {
+            block.render(area, buf);
+        }
+
+        let plot_area = self.block.inner_if_some(area);
+        if plot_area.is_empty() {
+            return;
+        }
+        let now = state.clock.read();
+        state.prune(now);
+
+        // Group the points by fade step, oldest first so newer points are drawn on top
+        let fade_time = state.fade_time.as_secs_f32().max(f32::EPSILON);
+        let mut steps: Vec<Vec<(f64, f64)>> = vec![Vec::new(); FADE_STEPS];
+        for (time, block) in &state.blocks {
+            let age = now.saturating_duration_since(*time).as_secs_f32() / fade_time;
+            let step = ((age * FADE_STEPS as f32) as usize).min(FADE_STEPS - 1);
+            steps[step].extend(block.iter().map(|&pair| self.transform(pair)));
+        }
+
+        let axes: &[(&str, (f64, f64))] = match self.mode {
+            GoniometerMode::Goniometer => {
+                &[("M", (0.0, 1.0)), ("L", (-0.7, 0.7)), ("R", (0.7, 0.7))]
+            }
+            GoniometerMode::Lissajous => &[("L", (1.0, 0.0)), ("R", (0.0, 1.0))],
+        };
+
+        Canvas::default()
+            .marker(self.marker)
+            .x_bounds([-1.0, 1.0])
+            .y_bounds([-1.0, 1.0])
+            .paint(|ctx| {
+                if self.show_axes {
+                    for &(_, (x, y)) in axes {
+                        ctx.draw(&Line::new(-x, -y, x, y, Color::DarkGray));
+                    }
+                    ctx.layer();
+                }
+                for (step, coords) in steps.iter().enumerate().rev() {
+                    ctx.draw(&Points {
+                        coords,
+                        color: self.shade(step),
+                    });
+                }
+                if self.show_axes {
+                    for &(name, (x, y)) in axes {
+                        ctx.print(x, y, name);
+                    }
+                }
+            })
+            .render(square_area(plot_area), buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plotted(goniometer: Goniometer, state: &mut GoniometerState) -> Vec<(u16, u16)> {
+        let area = Rect::new(0, 0, 20, 10);
+        let mut buf = Buffer::empty(area);
+        StatefulWidget::render(goniometer.show_axes(false), area, &mut buf, state);
+        area.positions()
+            .filter(|&p| buf[p].symbol() != " ")
+            .map(|p| (p.x, p.y))
+            .collect()
+    }
+
+    #[test]
+    fn mono_signal_is_a_vertical_line() {
+        let mut state = GoniometerState::default();
+        let samples: Vec<f32> = (-10..=10).flat_map(|i| [i as f32 / 15.0; 2]).collect();
+        state.push_interleaved(&samples);
+        let cells = plotted(Goniometer::default(), &mut state);
+        assert!(!cells.is_empty());
+        assert!(cells.iter().all(|&(x, _)| x == 9 || x == 10), "{cells:?}");
+    }
+
+    #[test]
+    fn lissajous_mode_is_not_rotated() {
+        let mut state = GoniometerState::default();
+        let samples: Vec<f32> = (-10..=10).flat_map(|i| [i as f32 / 15.0, 0.0]).collect();
+        state.push_interleaved(&samples);
+        let cells = plotted(
+            Goniometer::default().mode(GoniometerMode::Lissajous),
+            &mut state,
+        );
+        assert!(cells.iter().all(|&(_, y)| y == 4 || y == 5), "{cells:?}");
+    }
+
+    #[test]
+    fn old_points_fade_out() {
+        let mut state = GoniometerState::with_clock(Clock::Manual);
+        state.push_planar(&[0.5, 0.5]);
+        state.clock.update(Duration::from_millis(500));
+        assert!(!plotted(Goniometer::default(), &mut state).is_empty());
+        state.clock.update(Duration::from_millis(1));
+        assert!(plotted(Goniometer::default(), &mut state).is_empty());
+    }
+
+    #[test]
+    fn oldest_points_are_dropped_first() {
+        let mut state = GoniometerState {
+            max_points: 4,
+            ..Default::default()
+        };
+        state.push_interleaved(&[0.1; 6]);
+        state.push_interleaved(&[0.2; 4]);
+        assert_eq!(state.points, 4);
+        assert_eq!(state.blocks[0].1, [(0.1, 0.1); 2]);
+        assert_eq!(state.blocks[1].1, [(0.2, 0.2); 2]);
+    }
+
+    #[test]
+    fn oversized_block_keeps_its_newest_points() {
+        let mut state = GoniometerState {
+            max_points: 4,
+            ..Default::default()
+        };
+        let samples: Vec<f32> = (0..10).flat_map(|i| [i as f32 / 10.0; 2]).collect();
+        state.push_interleaved(&samples);
+        assert_eq!(state.points, 4);
+        assert_eq!(state.blocks[0].1.first(), Some(&(0.6, 0.6)));
+        assert!(!plotted(Goniometer::default(), &mut state).is_empty());
+    }
+}
@@ -0,0 +1,323 @@
+//! Colour gradients for the bars of the [`Meter`](crate::Meter) widget.
+
+use std::{env, sync::OnceLock};
+
+use ratatui::style::Color;
+
+/// The colour depth of the terminal a gradient is rendered on.
+///
+/// Gradient colours are computed as RGB and converted to the nearest colour the terminal can
+/// display.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorDepth {
+    /// 24-bit RGB colours.
+    #[default]
+    TrueColor,
+    /// The 256 colour xterm palette.
+    Indexed,
+    /// The 16 ANSI colours.
+    Ansi,
+}
+
+impl ColorDepth {
+    /// Detect the colour depth of the terminal from the `COLORTERM` and `TERM` environment
+    /// variables.
+    pub fn detect() -> Self {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorDepth::TrueColor;
+        }
+        let term = env::var("TERM").unwrap_or_default();
+        if term.contains("256color") {
+            ColorDepth::Indexed
+        } else {
+            ColorDepth::Ansi
+        }
+    }
+
+    /// The depth [detected](ColorDepth::detect) the first time it is needed, so the environment
+    /// is not read on every render.
+    pub(crate) fn detected() -> Self {
+        static DETECTED: OnceLock<ColorDepth> = OnceLock::new();
+        *DETECTED.get_or_init(ColorDepth::detect)
+    }
+
+    /// Convert an RGB colour to the nearest colour of this depth.
+    pub fn convert(self, (r, g, b): (u8, u8, u8)) -> Color {
+        match self {
+            ColorDepth::TrueColor => Color::Rgb(r, g, b),
+            ColorDepth::Indexed => Color::Indexed(nearest_indexed((r, g, b))),
+            ColorDepth::Ansi => {
+                let index = (0..16)
+                    .min_by_key(|&index| distance(ANSI_PALETTE[index], (r, g, b)))
+                    .unwrap_or(0);
+                ANSI_COLORS[index]
+            }
+        }
+    }
+}
+
+/// Brightness of the dimmed shade of a colour.
+const DIM_FACTOR: f32 = 0.3;
+
+/// A darker shade of `color` for a terminal with colour `depth`.
+///
+/// Returns `None` when the shade can not be shown at `depth`, so the caller can fall back to
+/// [`Modifier::DIM`](ratatui::style::Modifier::DIM).
+pub(crate) fn dimmed(color: Color, depth: ColorDepth) -> Option<Color> {
+    if depth == ColorDepth::Ansi {
+        return None;
+    }
+    let (r, g, b) = to_rgb(color)?;
+    let dim = |c: u8| (c as f32 * DIM_FACTOR).round() as u8;
+    Some(depth.convert((dim(r), dim(g), dim(b))))
+}
+
+/// A colour gradient along the decibel scale.
+///
+/// The gradient is defined by colour stops at decibel values. Levels between two stops are
+/// interpolated in RGB, levels outside the stops take the colour of the nearest stop.
+///
+/// ```
+/// use rataudio_meter::{Gradient, Meter};
+/// use ratatui::style::Color;
+///
+/// let gradient = Gradient::new([
+///     (-60.0, Color::Rgb(0, 160, 255)),
+///     (-18.0, Color::Rgb(0, 220, 120)),
+///     (0.0, Color::Rgb(255, 40, 40)),
+/// ]);
+/// let meter = Meter::stereo().gradient(gradient);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    stops: Vec<(f32, (u8, u8, u8))>,
+}
+
+impl Default for Gradient {
+    /// Green to yellow at -12 dBFS to red at 0 dBFS.
+    fn default() -> Self {
+        Self::new([
+            (-60.0, Color::Rgb(0, 200, 0)),
+            (-12.0, Color::Rgb(230, 220, 0)),
+            (0.0, Color::Rgb(230, 0, 0)),
+        ])
+    }
+}
+
+impl Gradient {
+    /// Create a new [`Gradient`] from colour stops given as decibel value and colour.
+    ///
+    /// Named and indexed colours are converted to their usual xterm RGB values.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `stops` is empty or contains [`Color::Reset`].
+    pub fn new(stops: impl IntoIterator<Item = (f32, Color)>) -> Self {
+        let mut stops: Vec<(f32, (u8, u8, u8))> = stops
+            .into_iter()
+            .map(|(db, color)| {
+                let rgb = to_rgb(color).expect("Gradient stops can not use Color::Reset.");
+                (db, rgb)
+            })
+            .collect();
+        assert!(
+            !stops.is_empty(),
+            "There should be at least one gradient stop."
+        );
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { stops }
+    }
+
+    /// Get the RGB colour of the gradient at `db`. A NaN level gets the colour of the first stop.
+    pub fn rgb(&self, db: f32) -> (u8, u8, u8) {
+        let first = self.stops[0];
+        let last = self.stops[self.stops.len() - 1];
+        if db.is_nan() || db <= first.0 {
+            return first.1;
+        }
+        if db >= last.0 {
+            return last.1;
+        }
+
+        let upper = self.stops.iter().position(|stop| stop.0 > db).unwrap_or(0);
+        let (low_db, low) = self.stops[upper - 1];
+        let (high_db, high) = self.stops[upper];
+        let t = (db - low_db) / (high_db - low_db);
+        let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        (
+            lerp(low.0, high.0),
+            lerp(low.1, high.1),
+            lerp(low.2, high.2),
+        )
+    }
+
+    /// Get the colour of the gradient at `db` for a terminal with colour `depth`.
+    pub fn color(&self, db: f32, depth: ColorDepth) -> Color {
+        depth.convert(self.rgb(db))
+    }
+}
+
+/// The default xterm values of the 16 ANSI colours.
+const ANSI_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The 16 ANSI colours in palette order.
+const ANSI_COLORS: [Color; 16] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
+
+/// Levels of each component in the 6x6x6 colour cube of the 256 colour palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Squared distance between two RGB colours.
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// RGB value of a colour of the 256 colour palette.
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI_PALETTE[index as usize],
+        16..=231 => {
+            let index = index - 16;
+            (
+                CUBE_LEVELS[(index / 36) as usize],
+                CUBE_LEVELS[(index / 6 % 6) as usize],
+                CUBE_LEVELS[(index % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + 10 * (index - 232);
+            (level, level, level)
+        }
+    }
+}
+
+/// Nearest colour of the colour cube and the grayscale ramp of the 256 colour palette.
+fn nearest_indexed(rgb: (u8, u8, u8)) -> u8 {
+    (16..=255)
+        .min_by_key(|&index| distance(indexed_rgb(index), rgb))
+        .unwrap_or(16)
+}
+
+/// RGB value of a colour, using the xterm palette for named and indexed colours.
+fn to_rgb(color: Color) -> Option<(u8, u8, u8)> {
+    let rgb = match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(index) => indexed_rgb(index),
+        named => {
+            let index = ANSI_COLORS.iter().position(|&ansi| ansi == named)?;
+            ANSI_PALETTE[index]
+        }
+    };
+    Some(rgb)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gradient_interpolates_between_stops() {
+        let gradient =
+            Gradient::new([(-20.0, Color::Rgb(0, 0, 0)), (0.0, Color::Rgb(200, 100, 0))]);
+        assert_eq!(gradient.rgb(-30.0), (0, 0, 0));
+        assert_eq!(gradient.rgb(-10.0), (100, 50, 0));
+        assert_eq!(gradient.rgb(3.0), (200, 100, 0));
+    }
+
+    #[test]
+    fn nan_gets_the_first_stop() {
+        let gradient =
+            Gradient::new([(-20.0, Color::Rgb(0, 0, 0)), (0.0, Color::Rgb(200, 100, 0))]);
+        assert_eq!(gradient.rgb(f32::NAN), (0, 0, 0));
+    }
+
+    #[test]
+    fn gradient_stops_are_sorted() {
+        let gradient = Gradient::new([(0.0, Color::Red), (-40.0, Color::Green)]);
+        assert_eq!(gradient.rgb(-40.0), (0, 205, 0));
+        assert_eq!(gradient.rgb(0.0), (205, 0, 0));
+    }
+
+    #[test]
+    fn true_color_is_kept() {
+        assert_eq!(
+            ColorDepth::TrueColor.convert((12, 34, 56)),
+            Color::Rgb(12, 34, 56)
+        );
+    }
+
+    #[test]
+    fn indexed_fallback_uses_nearest_palette_colour() {
+        assert_eq!(
+            ColorDepth::Indexed.convert((255, 0, 0)),
+            Color::Indexed(196)
+        );
+        assert_eq!(
+            ColorDepth::Indexed.convert((0, 95, 135)),
+            Color::Indexed(24)
+        );
+        assert_eq!(
+            ColorDepth::Indexed.convert((128, 128, 128)),
+            Color::Indexed(244)
+        );
+    }
+
+    #[test]
+    fn ansi_fallback_uses_nearest_named_colour() {
+        assert_eq!(ColorDepth::Ansi.convert((250, 10, 10)), Color::LightRed);
+        assert_eq!(ColorDepth::Ansi.convert((0, 190, 0)), Color::Green);
+        assert_eq!(ColorDepth::Ansi.convert((230, 220, 0)), Color::Yellow);
+    }
+
+    #[test]
+    fn dimmed_colours_are_darker() {
+        assert_eq!(
+            dimmed(Color::Rgb(200, 100, 0), ColorDepth::TrueColor),
+            Some(Color::Rgb(60, 30, 0))
+        );
+        assert_eq!(dimmed(Color::Red, ColorDepth::Ansi), None);
+        assert_eq!(dimmed(Color::Reset, ColorDepth::TrueColor), None);
+    }
+
+    #[test]
+    #[should_panic = "There should be at least one gradient stop."]
+    fn gradient_without_stops_panics() {
+        let _ = Gradient::new([]);
+    }
+}
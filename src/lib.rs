@@ -1,8 +1,42 @@
+mod analysis;
+mod ballistics;
 mod constants;
+mod correlation;
+mod correlation_meter;
+mod goniometer;
+mod gradient;
+mod loudness;
+mod loudness_meter;
 mod meter;
+mod meter_bridge;
+#[cfg(feature = "remote")]
+mod remote;
 mod rendering;
 mod scaling;
+mod shared;
+mod spectrum;
+mod spectrum_meter;
 mod state;
+mod ticks;
+mod true_peak;
+mod waveform;
 
-pub use meter::{Meter, MeterInput};
-pub use state::MeterState;
+pub use analysis::{LevelAnalyzer, SampleLayout};
+pub use ballistics::Ballistics;
+pub use correlation::CorrelationAnalyzer;
+pub use correlation_meter::{CorrelationMeter, CorrelationState};
+pub use goniometer::{Goniometer, GoniometerMode, GoniometerState};
+pub use gradient::{ColorDepth, Gradient};
+pub use loudness::{LoudnessAnalyzer, LoudnessReading};
+pub use loudness_meter::{LoudnessMeter, LoudnessScale};
+pub use meter::{BarMode, ColorZone, LabelValue, Meter, MeterInput, Segments};
+pub use meter_bridge::{MeterBridge, MeterBridgeState, MeterStrip};
+#[cfg(feature = "remote")]
+pub use remote::{LevelReceiver, LevelUnit};
+pub use scaling::{Iec60268, LinearDb, PowerLaw, ScaleMapping};
+pub use shared::SharedLevels;
+pub use spectrum::{Banding, SpectrumAnalyzer, SpectrumBand, WindowFunction};
+pub use spectrum_meter::{SpectrumMeter, SpectrumState};
+pub use state::{Clock, Clocked, MeterState, PeakHoldMode};
+pub use true_peak::TruePeakDetector;
+pub use waveform::{Waveform, WaveformMode, WaveformScale, WaveformState};
@@ -0,0 +1,509 @@
+//! Loudness measurement according to ITU-R BS.1770 and EBU R128.
+//!
+//! A [`LoudnessAnalyzer`] applies the K-weighting filter to blocks of samples and reports the
+//! momentary (400 ms), short-term (3 s) and gated integrated loudness in LUFS, as well as the
+//! loudness range (LRA) according to EBU Tech 3342. The result can be displayed with the
+//! [`LoudnessMeter`](crate::LoudnessMeter) widget.
+
+use crate::analysis::SampleLayout;
+
+/// Number of 100 ms sub-blocks in the momentary window (400 ms).
+const MOMENTARY_BLOCKS: usize = 4;
+/// Number of 100 ms sub-blocks in the short-term window (3 s).
+const SHORT_TERM_BLOCKS: usize = 30;
+/// Absolute gate threshold in LUFS.
+const ABSOLUTE_GATE: f64 = -70.0;
+/// Relative gate for the integrated loudness in LU.
+const INTEGRATED_RELATIVE_GATE: f64 = -10.0;
+/// Relative gate for the loudness range in LU.
+const RANGE_RELATIVE_GATE: f64 = -20.0;
+/// Width in LU of the bins of a [`Histogram`].
+const BIN_WIDTH: f64 = 0.1;
+/// Number of bins of a [`Histogram`], from the absolute gate up to +30 LUFS.
+const HISTOGRAM_BINS: usize = 1000;
+
+/// A snapshot of the loudness measurements of a [`LoudnessAnalyzer`].
+///
+/// Loudness values are in LUFS and the loudness range in LU. Values that can not be computed yet
+/// are [`f32::NEG_INFINITY`] (loudness) or `0.0` (range).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessReading {
+    pub momentary: f32,
+    pub short_term: f32,
+    pub integrated: f32,
+    pub range: f32,
+}
+
+impl Default for LoudnessReading {
+    fn default() -> Self {
+        Self {
+            momentary: f32::NEG_INFINITY,
+            short_term: f32::NEG_INFINITY,
+            integrated: f32::NEG_INFINITY,
+            range: 0.0,
+        }
+    }
+}
+
+/// A second order IIR filter section in direct form I.
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self {
+            b,
+            a,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+/// The two stage K-weighting filter of BS.1770, computed for any sample rate.
+#[derive(Debug, Clone, Copy)]
+struct KWeighting {
+    shelf: Biquad,
+    high_pass: Biquad,
+}
+
+impl KWeighting {
+    fn new(sample_rate: u32) -> Self {
+        let fs = sample_rate as f64;
+
+        // Stage 1: high shelf modelling the acoustic effect of the head
+        let f0 = 1681.974450955533;
+        let gain = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (std::f64::consts::PI * f0 / fs).tan();
+        let vh = 10_f64.powf(gain / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad::new(
+            [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        // Stage 2: RLB high pass
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (std::f64::consts::PI * f0 / fs).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = Biquad::new(
+            [1.0, -2.0, 1.0],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        Self { shelf, high_pass }
+    }
+
+    fn process(&mut self, x: f32) -> f64 {
+        self.high_pass.process(self.shelf.process(x as f64))
+    }
+}
+
+/// Loudness measurement of multichannel audio according to ITU-R BS.1770-4.
+///
+/// The analyzer works on 100 ms sub-blocks: the momentary and short-term loudness are updated
+/// every 100 ms, and the gating blocks used for the integrated loudness and the loudness range
+/// overlap by 75% and 97% respectively.
+#[derive(Debug, Clone)]
+pub struct LoudnessAnalyzer {
+    channels: usize,
+    sample_rate: u32,
+    weights: Vec<f64>,
+    filters: Vec<KWeighting>,
+    sub_block_len: usize,
+    sub_block_pos: usize,
+    sub_block_sums: Vec<f64>,
+    /// Weighted mean square of the most recent sub-blocks, newest last.
+    recent: Vec<f64>,
+    /// Mean square of every 400 ms gating block.
+    gating_blocks: Histogram,
+    /// Mean square of every 3 s short-term window.
+    short_term_blocks: Histogram,
+}
+
+impl LoudnessAnalyzer {
+    /// Create a new analyzer for `channels` channels at `sample_rate` Hz.
+    ///
+    /// Six channel input is assumed to be 5.1 in the order L, R, C, LFE, Ls, Rs, where the LFE
+    /// channel is ignored and the surround channels are weighted by +1.5 dB. All channels of
+    /// other layouts are weighted equally, use [`LoudnessAnalyzer::channel_weights`] to change
+    /// this.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `channels` is zero or `sample_rate` is below 10 Hz.
+    pub fn new(channels: usize, sample_rate: u32) -> Self {
+        assert!(channels > 0, "Channel count should be at least 1.");
+        assert!(sample_rate >= 10, "Sample rate should be at least 10 Hz.");
+        let weights = if channels == 6 {
+            vec![1.0, 1.0, 1.0, 0.0, 1.41, 1.41]
+        } else {
+            vec![1.0; channels]
+        };
+        Self {
+            channels,
+            sample_rate,
+            weights,
+            filters: vec![KWeighting::new(sample_rate); channels],
+            sub_block_len: (sample_rate / 10) as usize,
+            sub_block_pos: 0,
+            sub_block_sums: vec![0.0; channels],
+            recent: Vec::with_capacity(SHORT_TERM_BLOCKS),
+            gating_blocks: Histogram::new(),
+            short_term_blocks: Histogram::new(),
+        }
+    }
+
+    /// Set the weight of every channel in the loudness sum.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the number of weights does not match the channel count.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn channel_weights(mut self, weights: &[f32]) -> Self {
+        assert!(
+            weights.len() == self.channels,
+            "There should be one weight per channel."
+        );
+        self.weights = weights.iter().map(|&w| w as f64).collect();
+        self
+    }
+
+    /// Get the number of channels for this [`LoudnessAnalyzer`].
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Analyze a block of samples in the given layout.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the length of `samples` is not a multiple of the channel count.
+    pub fn process(&mut self, samples: &[f32], layout: SampleLayout) {
+        assert!(
+            samples.len().is_multiple_of(self.channels),
+            "Sample count should be a multiple of the channel count."
+        );
+        let frames = samples.len() / self.channels;
+        for frame in 0..frames {
+            for channel in 0..self.channels {
+                let sample = match layout {
+                    SampleLayout::Interleaved => samples[frame * self.channels + channel],
+                    SampleLayout::Planar => samples[channel * frames + frame],
+                };
+                let filtered = self.filters[channel].process(sample);
+                self.sub_block_sums[channel] += filtered * filtered;
+            }
+
+            self.sub_block_pos += 1;
+            if self.sub_block_pos == self.sub_block_len {
+                self.finish_sub_block();
+            }
+        }
+    }
+
+    /// Analyze a block of interleaved samples.
+    pub fn process_interleaved(&mut self, samples: &[f32]) {
+        self.process(samples, SampleLayout::Interleaved);
+    }
+
+    /// Analyze a block of planar samples.
+    pub fn process_planar(&mut self, samples: &[f32]) {
+        self.process(samples, SampleLayout::Planar);
+    }
+
+    fn finish_sub_block(&mut self) {
+        let len = self.sub_block_len as f64;
+        let energy = self
+            .sub_block_sums
+            .iter()
+            .zip(&self.weights)
+            .map(|(sum, weight)| weight * sum / len)
+            .sum();
+        self.sub_block_sums.fill(0.0);
+        self.sub_block_pos = 0;
+
+        if self.recent.len() == SHORT_TERM_BLOCKS {
+            self.recent.remove(0);
+        }
+        self.recent.push(energy);
+
+        if self.recent.len() >= MOMENTARY_BLOCKS {
+            self.gating_blocks.add(self.window_energy(MOMENTARY_BLOCKS));
+        }
+        if self.recent.len() >= SHORT_TERM_BLOCKS {
+            self.short_term_blocks
+                .add(self.window_energy(SHORT_TERM_BLOCKS));
+        }
+    }
+
+    /// Mean square of the last `blocks` sub-blocks, where missing sub-blocks count as silence.
+    fn window_energy(&self, blocks: usize) -> f64 {
+        let start = self.recent.len().saturating_sub(blocks);
+        self.recent[start..].iter().sum::<f64>() / blocks as f64
+    }
+
+    /// Momentary loudness in LUFS.
+    pub fn momentary(&self) -> f32 {
+        loudness(self.window_energy(MOMENTARY_BLOCKS)) as f32
+    }
+
+    /// Short-term loudness in LUFS.
+    pub fn short_term(&self) -> f32 {
+        loudness(self.window_energy(SHORT_TERM_BLOCKS)) as f32
+    }
+
+    /// Gated integrated loudness in LUFS since the start of the measurement.
+    ///
+    /// The relative gate is applied with a resolution of 0.1 LU.
+    pub fn integrated(&self) -> f32 {
+        let Some(above_absolute) = self.gating_blocks.mean_energy(0) else {
+            return f32::NEG_INFINITY;
+        };
+        let relative_gate = loudness(above_absolute) + INTEGRATED_RELATIVE_GATE;
+        let start = Histogram::first_bin_above(relative_gate);
+        self.gating_blocks
+            .mean_energy(start)
+            .map_or(f32::NEG_INFINITY, |energy| loudness(energy) as f32)
+    }
+
+    /// Loudness range in LU according to EBU Tech 3342.
+    ///
+    /// The relative gate is applied with a resolution of 0.1 LU.
+    pub fn loudness_range(&self) -> f32 {
+        let Some(above_absolute) = self.short_term_blocks.mean_energy(0) else {
+            return 0.0;
+        };
+        let relative_gate = loudness(above_absolute) + RANGE_RELATIVE_GATE;
+        let start = Histogram::first_bin_above(relative_gate);
+        match (
+            self.short_term_blocks.percentile(start, 0.95),
+            self.short_term_blocks.percentile(start, 0.10),
+        ) {
+            (Some(high), Some(low)) => (high - low) as f32,
+            _ => 0.0,
+        }
+    }
+
+    /// All current measurements.
+    pub fn reading(&self) -> LoudnessReading {
+        LoudnessReading {
+            momentary: self.momentary(),
+            short_term: self.short_term(),
+            integrated: self.integrated(),
+            range: self.loudness_range(),
+        }
+    }
+
+    /// Reset all measurements and the filter state.
+    pub fn reset(&mut self) {
+        *self = Self {
+            weights: std::mem::take(&mut self.weights),
+            ..Self::new(self.channels, self.sample_rate)
+        };
+    }
+}
+
+/// Loudness in LUFS of a weighted mean square.
+fn loudness(energy: f64) -> f64 {
+    if energy > 0.0 {
+        -0.691 + 10.0 * energy.log10()
+    } else {
+        f64::NEG_INFINITY
+    }
+}
+
+/// Blocks counted in bins of [`BIN_WIDTH`] LU above the absolute gate, together with the sum of
+/// their mean squares, as in libebur128.
+///
+/// The memory and the time to gate the blocks do not grow with the length of the measurement.
+#[derive(Debug, Clone)]
+struct Histogram {
+    counts: Vec<u64>,
+    energies: Vec<f64>,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            counts: vec![0; HISTOGRAM_BINS],
+            energies: vec![0.0; HISTOGRAM_BINS],
+        }
+    }
+
+    /// Add a block with mean square `energy`. Blocks at or below the absolute gate are dropped.
+    fn add(&mut self, energy: f64) {
+        let loudness = loudness(energy);
+        if loudness <= ABSOLUTE_GATE {
+            return;
+        }
+        let bin = (((loudness - ABSOLUTE_GATE) / BIN_WIDTH) as usize).min(HISTOGRAM_BINS - 1);
+        self.counts[bin] += 1;
+        self.energies[bin] += energy;
+    }
+
+    /// Loudness in LUFS of the mean square of the blocks in `bin`.
+    fn mean_loudness(&self, bin: usize) -> f64 {
+        loudness(self.energies[bin] / self.counts[bin] as f64)
+    }
+
+    /// First bin with a centre above `gate` LUFS.
+    fn first_bin_above(gate: f64) -> usize {
+        let bin = ((gate - ABSOLUTE_GATE) / BIN_WIDTH - 0.5).floor() + 1.0;
+        bin.clamp(0.0, HISTOGRAM_BINS as f64) as usize
+    }
+
+    /// Mean square of the blocks in the bins from `start`, if there are any.
+    fn mean_energy(&self, start: usize) -> Option<f64> {
+        let count: u64 = self.counts[start..].iter().sum();
+        (count > 0).then(|| self.energies[start..].iter().sum::<f64>() / count as f64)
+    }
+
+    /// Loudness at percentile `p` of the blocks in the bins from `start` using the nearest rank,
+    /// if there are any. The loudness of a block is taken as the mean loudness of its bin.
+    fn percentile(&self, start: usize, p: f64) -> Option<f64> {
+        let count: u64 = self.counts[start..].iter().sum();
+        if count == 0 {
+            return None;
+        }
+        let rank = ((count - 1) as f64 * p).round() as u64;
+        let mut seen = 0;
+        (start..HISTOGRAM_BINS).find_map(|bin| {
+            seen += self.counts[bin];
+            (seen > rank).then(|| self.mean_loudness(bin))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48_000;
+
+    /// Stereo interleaved 1 kHz sine at `db` dBFS for `seconds`.
+    fn sine(db: f32, seconds: f32) -> Vec<f32> {
+        let amplitude = 10_f32.powf(db / 20.0);
+        (0..(seconds * SAMPLE_RATE as f32) as usize)
+            .flat_map(|i| {
+                let phase = i as f32 * 1000.0 / SAMPLE_RATE as f32 * std::f32::consts::TAU;
+                let s = amplitude * phase.sin();
+                [s, s]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn k_weighting_coefficients_at_48k() {
+        let k = KWeighting::new(48_000);
+        let expected_b = [1.53512485958697, -2.69169618940638, 1.19839281085285];
+        let expected_a = [-1.69065929318241, 0.73248077421585];
+        for (b, e) in k.shelf.b.iter().zip(expected_b) {
+            assert!((b - e).abs() < 1e-9);
+        }
+        for (a, e) in k.shelf.a.iter().zip(expected_a) {
+            assert!((a - e).abs() < 1e-9);
+        }
+        assert!((k.high_pass.a[0] + 1.99004745483398).abs() < 1e-9);
+        assert!((k.high_pass.a[1] - 0.99007225036621).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stereo_sine_at_minus_23_dbfs_reads_minus_23_lufs() {
+        // EBU Tech 3341 test case 1
+        let mut analyzer = LoudnessAnalyzer::new(2, SAMPLE_RATE);
+        analyzer.process_interleaved(&sine(-23.0, 20.0));
+        let reading = analyzer.reading();
+        assert!((reading.momentary + 23.0).abs() < 0.1, "{reading:?}");
+        assert!((reading.short_term + 23.0).abs() < 0.1, "{reading:?}");
+        assert!((reading.integrated + 23.0).abs() < 0.1, "{reading:?}");
+        assert!(reading.range < 0.1, "{reading:?}");
+    }
+
+    #[test]
+    fn relative_gate_ignores_quiet_parts() {
+        // EBU Tech 3341 test case 3: -36, -23 and -36 dBFS tones gate to -23 LUFS
+        let mut analyzer = LoudnessAnalyzer::new(2, SAMPLE_RATE);
+        analyzer.process_interleaved(&sine(-36.0, 10.0));
+        analyzer.process_interleaved(&sine(-23.0, 60.0));
+        analyzer.process_interleaved(&sine(-36.0, 10.0));
+        assert!((analyzer.integrated() + 23.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn loudness_range_of_two_levels() {
+        // EBU Tech 3342 test case 1: -20 and -30 dBFS tones give an LRA of 10 LU
+        let mut analyzer = LoudnessAnalyzer::new(2, SAMPLE_RATE);
+        analyzer.process_interleaved(&sine(-20.0, 20.0));
+        analyzer.process_interleaved(&sine(-30.0, 20.0));
+        assert!((analyzer.loudness_range() - 10.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn histogram_gates_and_ranks_by_bin() {
+        let mut histogram = Histogram::new();
+        for lufs in [-30.0, -20.0, -20.0, -10.0] {
+            histogram.add(10_f64.powf((lufs + 0.691) / 10.0));
+        }
+        histogram.add(0.0);
+        assert_eq!(histogram.counts.iter().sum::<u64>(), 4);
+
+        let start = Histogram::first_bin_above(-25.0);
+        // bin 450 is centred on -24.95 LUFS
+        assert_eq!(start, 450);
+        let mean = loudness(histogram.mean_energy(start).unwrap());
+        assert!((mean + 13.98).abs() < 0.01, "mean: {mean}");
+        let low = histogram.percentile(0, 0.0).unwrap();
+        let high = histogram.percentile(0, 1.0).unwrap();
+        assert!((low + 30.0).abs() < 1e-9 && (high + 10.0).abs() < 1e-9);
+        assert_eq!(histogram.percentile(HISTOGRAM_BINS, 0.5), None);
+    }
+
+    #[test]
+    fn silence_is_negative_infinity() {
+        let mut analyzer = LoudnessAnalyzer::new(1, SAMPLE_RATE);
+        analyzer.process_interleaved(&[0.0; 48_000]);
+        assert_eq!(analyzer.reading(), LoudnessReading::default());
+    }
+
+    #[test]
+    fn reset_clears_measurements() {
+        let mut analyzer = LoudnessAnalyzer::new(2, SAMPLE_RATE);
+        analyzer.process_interleaved(&sine(-23.0, 1.0));
+        analyzer.reset();
+        assert_eq!(analyzer.reading(), LoudnessReading::default());
+    }
+
+    #[test]
+    fn reset_keeps_the_sample_rate() {
+        let samples = sine(-23.0, 1.0);
+        let mut fresh = LoudnessAnalyzer::new(2, 44_105);
+        fresh.process_interleaved(&samples);
+        let mut reset = LoudnessAnalyzer::new(2, 44_105);
+        reset.process_interleaved(&samples);
+        reset.reset();
+        reset.process_interleaved(&samples);
+        assert_eq!(reset.reading(), fresh.reading());
+    }
+}
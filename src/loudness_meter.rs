@@ -0,0 +1,368 @@
+//! The [`LoudnessMeter`] widget is used to display EBU R128 loudness measurements.
+
+use std::sync::Arc;
+
+use ratatui::{
+    layout::Direction,
+    prelude::{symbols, BlockExt, Buffer, Rect, Style, Widget},
+    widgets::{Block, Paragraph},
+};
+
+use crate::{
+    loudness::LoudnessReading,
+    rendering::{render_ticks, Bar, MeterAreas, Zones},
+    scaling::{DbScale, LinearDb},
+    ticks::{format_db, layout_ticks_with, TickSteps},
+};
+
+/// The yellow zone starts when the loudness enters the ±1 LU tolerance around the target.
+const YELLOW_START_LU: f32 = -1.0;
+/// The red zone starts when the loudness exceeds the ±1 LU tolerance around the target.
+const RED_START_LU: f32 = 1.0;
+
+/// Range of the scale of a [`LoudnessMeter`] as defined in EBU Tech 3341.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoudnessScale {
+    /// "EBU +9" scale from -18 LU to +9 LU around the target.
+    #[default]
+    Ebu9,
+    /// "EBU +18" scale from -36 LU to +18 LU around the target.
+    Ebu18,
+}
+
+impl LoudnessScale {
+    /// Lower and upper end of the scale in LU relative to the target.
+    fn range(&self) -> (f32, f32) {
+        match self {
+            LoudnessScale::Ebu9 => (-18.0, 9.0),
+            LoudnessScale::Ebu18 => (-36.0, 18.0),
+        }
+    }
+
+    /// Steps in LU of the labels and minor ticks, so the labels fall on the EBU Tech 3341 marks
+    /// when there is room.
+    fn tick_steps(&self) -> TickSteps {
+        match self {
+            LoudnessScale::Ebu9 => TickSteps {
+                labels: &[9.0, 3.0],
+                minor: &[3.0, 1.0],
+            },
+            LoudnessScale::Ebu18 => TickSteps {
+                labels: &[18.0, 6.0],
+                minor: &[6.0, 2.0],
+            },
+        }
+    }
+}
+
+/// A widget to display loudness measurements.
+///
+/// A `LoudnessMeter` renders three bars for the momentary (M), short-term (S) and integrated (I)
+/// loudness of a [`LoudnessReading`] on a LUFS or LU scale. The loudness range (LRA) is shown in
+/// the label of the integrated loudness.
+///
+/// The bars use the same colour zones as the [`Meter`](crate::Meter): green below the target,
+/// yellow within the ±1 LU tolerance around the target and red above.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoudnessMeter<'a> {
+    pub(crate) block: Option<Block<'a>>,
+    pub(crate) reading: LoudnessReading,
+    pub(crate) target: f32,
+    pub(crate) scale: LoudnessScale,
+    pub(crate) relative: bool,
+    pub(crate) show_labels: bool,
+    pub(crate) show_scale: bool,
+    pub(crate) direction: Direction,
+}
+
+impl Default for LoudnessMeter<'_> {
+    fn default() -> Self {
+        Self {
+            block: None,
+            reading: LoudnessReading::default(),
+            target: -23.0,
+            scale: LoudnessScale::default(),
+            relative: false,
+            show_labels: true,
+            show_scale: true,
+            direction: Direction::Horizontal,
+        }
+    }
+}
+
+impl<'a> LoudnessMeter<'a> {
+    /// Create a new [`LoudnessMeter`] widget displaying `reading`.
+    pub fn new(reading: LoudnessReading) -> Self {
+        Self {
+            reading,
+            ..Default::default()
+        }
+    }
+
+    /// Surrounds the `LoudnessMeter` with a [`Block`].
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    /// Set the loudness target in LUFS. Defaults to -23 LUFS as in EBU R128.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn target(mut self, target: f32) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Set the range of the scale.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn scale(mut self, scale: LoudnessScale) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Show values in LU relative to the target instead of absolute LUFS.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn relative(mut self, relative: bool) -> Self {
+        self.relative = relative;
+        self
+    }
+
+    /// Show or hide the loudness labels for the [`LoudnessMeter`].
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn show_labels(mut self, show: bool) -> Self {
+        self.show_labels = show;
+        self
+    }
+
+    /// Show or hide the scale of the [`LoudnessMeter`].
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn show_scale(mut self, show: bool) -> Self {
+        self.show_scale = show;
+        self
+    }
+
+    /// Set the direction the bars of the [`LoudnessMeter`] fill in.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Convert a loudness relative to the target to a ratio of the scale.
+    fn lu_to_ratio(&self, lu: f32) -> f32 {
+        let (floor, ceiling) = self.scale.range();
+        if lu.is_nan() {
+            return 0.0;
+        }
+        ((lu - floor) / (ceiling - floor)).clamp(0.0, 1.0)
+    }
+
+    /// Format a loudness in LUFS for the labels.
+    fn format_loudness(&self, lufs: f32) -> String {
+        let unit = match (self.relative, self.direction) {
+            (_, Direction::Vertical) => "",
+            (true, Direction::Horizontal) => " LU",
+            (false, Direction::Horizontal) => " LUFS",
+        };
+        if lufs == f32::NEG_INFINITY {
+            format!("-∞{unit}")
+        } else if self.relative {
+            format!("{:+.1}{unit}", lufs - self.target)
+        } else {
+            format!("{lufs:.1}{unit}")
+        }
+    }
+
+    fn render_scale(&self, label_area: Rect, buf: &mut Buffer) {
+        let (floor, ceiling) = self.scale.range();
+        let length = match self.direction {
+            Direction::Horizontal => label_area.width,
+            Direction::Vertical => label_area.height,
+        };
+        let scale = DbScale {
+            floor_db: floor,
+            ceiling_db: ceiling,
+            mapping: Arc::new(LinearDb),
+        };
+        let ticks = layout_ticks_with(
+            &scale,
+            length,
+            self.direction,
+            &self.scale.tick_steps(),
+            |lu| {
+                if self.relative {
+                    format_db(lu)
+                } else {
+                    format_db(lu + self.target)
+                }
+            },
+        );
+        render_ticks(&ticks, self.direction, label_area, buf, Style::default());
+    }
+}
+
+impl Widget for LoudnessMeter<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Widget::render(&self, area, buf);
+    }
+}
+
+impl Widget for &LoudnessMeter<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if let Some(block) = self.block.as_ref() {
+            block.render(area, buf);
+        }
+
+        let meter_area = self.block.inner_if_some(area);
+        if meter_area.is_empty() {
+            return;
+        }
+
+        let values = [
+            ("M", self.reading.momentary),
+            ("S", self.reading.short_term),
+            ("I", self.reading.integrated),
+        ];
+        let areas = MeterAreas::new(
+            meter_area,
+            values.len(),
+            self.show_labels,
+            self.show_scale,
+            self.direction,
+        );
+
+        for (index, (name, lufs)) in values.into_iter().enumerate() {
+            let bar = Bar {
+                area: areas.meters[index],
+                direction: self.direction,
+            };
+            let len = bar.len();
+            let zones = Zones::traffic_light(
+                len,
+                self.lu_to_ratio(YELLOW_START_LU),
+                self.lu_to_ratio(RED_START_LU),
+            );
+
+            // --- METER BARS ---
+            let ratio = self.lu_to_ratio(lufs - self.target);
+            if ratio > 0.0 {
+                let fill = (len as f32 * ratio).round() as u16;
+                for pos in 0..=fill.min(len.saturating_sub(1)) {
+                    bar.set(buf, pos, symbols::block::SEVEN_EIGHTHS, zones.style(pos));
+                }
+            }
+
+            // --- LOUDNESS LABEL ---
+            if let Some(label_areas) = areas.labels.as_ref() {
+                let text = match (self.direction, name) {
+                    (Direction::Horizontal, "I") => format!(
+                        "I {}  LRA {:.1} LU",
+                        self.format_loudness(lufs),
+                        self.reading.range
+                    ),
+                    (Direction::Horizontal, _) => format!("{name} {}", self.format_loudness(lufs)),
+                    (Direction::Vertical, _) => self.format_loudness(lufs),
+                };
+                Paragraph::new(text).render(label_areas[index], buf);
+            }
+        }
+
+        // --- SCALE LABELS ---
+        if let Some(scale_area) = areas.scale {
+            self.render_scale(scale_area, buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::Color;
+
+    fn reading(lufs: f32) -> LoudnessReading {
+        LoudnessReading {
+            momentary: lufs,
+            short_term: lufs,
+            integrated: lufs,
+            range: 4.5,
+        }
+    }
+
+    #[test]
+    fn target_is_in_the_tolerance_zone() {
+        let meter = LoudnessMeter::new(reading(-23.0)).show_labels(false);
+        let area = Rect::new(0, 0, 27, 4);
+        let mut buf = Buffer::empty(area);
+        meter.render(area, &mut buf);
+
+        // -23 LUFS is 18 LU above the bottom of the 27 LU wide EBU +9 scale
+        assert_eq!(buf[(18, 0)].fg, Color::Yellow);
+        assert_eq!(buf[(19, 0)].symbol(), " ");
+        assert_eq!(buf[(10, 2)].fg, Color::Green);
+    }
+
+    #[test]
+    fn labels_show_loudness_and_range() {
+        let meter = LoudnessMeter::new(reading(-20.0)).show_scale(false);
+        let area = Rect::new(0, 0, 40, 6);
+        let mut buf = Buffer::empty(area);
+        meter.render(area, &mut buf);
+
+        let line = |y: u16| -> String { (0..40).map(|x| buf[(x, y)].symbol()).collect() };
+        assert_eq!(line(0).trim_end(), "M -20.0 LUFS");
+        assert_eq!(line(2).trim_end(), "I -20.0 LUFS  LRA 4.5 LU");
+    }
+
+    #[test]
+    fn relative_labels() {
+        let meter = LoudnessMeter::new(reading(-20.0))
+            .relative(true)
+            .show_scale(false);
+        let area = Rect::new(0, 0, 40, 6);
+        let mut buf = Buffer::empty(area);
+        meter.render(area, &mut buf);
+
+        let line: String = (0..40).map(|x| buf[(x, 1)].symbol()).collect();
+        assert_eq!(line.trim_end(), "S +3.0 LU");
+    }
+
+    #[test]
+    fn silence_renders_empty_bars() {
+        let meter = LoudnessMeter::new(LoudnessReading::default()).show_labels(false);
+        let area = Rect::new(0, 0, 28, 4);
+        let mut buf = Buffer::empty(area);
+        meter.render(area, &mut buf);
+
+        assert!((0..28).all(|x| buf[(x, 0)].symbol() == " "));
+    }
+
+    #[test]
+    fn scale_labels_adapt_to_the_length() {
+        let render = |meter: LoudnessMeter, width: u16| -> String {
+            let area = Rect::new(0, 0, width, 4);
+            let mut buf = Buffer::empty(area);
+            meter.show_labels(false).render(area, &mut buf);
+            (0..width).map(|x| buf[(x, 3)].symbol()).collect()
+        };
+        assert_eq!(render(LoudnessMeter::default(), 12), "-41  · · -14");
+        assert_eq!(
+            render(LoudnessMeter::default(), 28),
+            "-41 · · -32 ·   · -23 ·  -14"
+        );
+        assert_eq!(
+            render(LoudnessMeter::default().relative(true), 28),
+            "-18 · · -9  ·  -3  0  · · +9"
+        );
+        let wide = render(
+            LoudnessMeter::default()
+                .relative(true)
+                .scale(LoudnessScale::Ebu18),
+            60,
+        );
+        assert!(wide.starts_with("-36 · -30   -24"), "{wide}");
+        assert!(wide.ends_with("+12  +18"), "{wide}");
+
+        let offset = render(LoudnessMeter::default().target(-23.5), 12);
+        assert_eq!(offset, "-42  · · -14", "{offset}");
+    }
+}
@@ -1,29 +1,285 @@
-//! The [`Meter`] widget is used to display a horizontal audio meter.
+//! The [`Meter`] widget is used to display a horizontal or vertical audio meter.
 
-use crate::scaling::MeterScale;
-use ratatui::widgets::Block;
+use crate::{
+    constants::{MIN_DB, RED_START_DB, YELLOW_START_DB},
+    gradient::{ColorDepth, Gradient},
+    scaling::{DbScale, MeterScale, ScaleMapping},
+};
+use ratatui::{
+    layout::Direction,
+    style::{Color, Style},
+    widgets::Block,
+};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 
 /// Input type for the [`Meter`] widget
+///
+/// Use [`MeterInput::Channels`] for meters with more than two channels. It can also be built from
+/// a slice or an iterator of levels:
+///
+/// ```
+/// use rataudio_meter::MeterInput;
+///
+/// let levels = [-6.0, -12.0, -3.0, -18.0, -20.0, -9.0];
+/// let input = MeterInput::from(&levels[..]);
+/// let input: MeterInput = levels.iter().copied().collect();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
 pub enum MeterInput {
     Mono(f32),
     Stereo(f32, f32),
+    Channels(Vec<f32>),
+}
+
+impl MeterInput {
+    /// Consume the input and return the values in channel order.
+    pub fn into_values(self) -> Vec<f32> {
+        match self {
+            MeterInput::Mono(value) => vec![value],
+            MeterInput::Stereo(left, right) => vec![left, right],
+            MeterInput::Channels(values) => values,
+        }
+    }
+}
+
+impl From<&[f32]> for MeterInput {
+    fn from(values: &[f32]) -> Self {
+        MeterInput::Channels(values.to_vec())
+    }
+}
+
+impl FromIterator<f32> for MeterInput {
+    fn from_iter<I: IntoIterator<Item = f32>>(iter: I) -> Self {
+        MeterInput::Channels(iter.into_iter().collect())
+    }
+}
+
+/// How the bars of a [`Meter`] are drawn
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BarMode {
+    /// Fill whole cells.
+    #[default]
+    Cells,
+    /// Fill the leading edge of the bar and place the peak hold marker with partial block
+    /// characters, for eight steps per cell.
+    Eighths,
+    /// Draw the bar as separate segments with gaps, like the LEDs of a hardware meter.
+    Segments(Segments),
+}
+
+/// Layout of the segments of a [`Meter`] drawn with [`BarMode::Segments`]
+///
+/// The bar is divided into `count` segments of equal size if it is set, or else into as many
+/// segments of `size` cells as fit. Segments are separated by `gap` cells. A segment is lit when
+/// the level reaches it. Unlit segments are drawn in a darker shade of their zone colour if
+/// `dim_unlit` is set and left off otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Segments {
+    pub count: Option<u16>,
+    pub size: u16,
+    pub gap: u16,
+    pub dim_unlit: bool,
+}
+
+impl Default for Segments {
+    fn default() -> Self {
+        Self {
+            count: None,
+            size: 1,
+            gap: 1,
+            dim_unlit: true,
+        }
+    }
+}
+
+impl Segments {
+    /// Divide the bar into `count` segments.
+    pub fn with_count(count: u16) -> Self {
+        Self {
+            count: Some(count),
+            ..Default::default()
+        }
+    }
+
+    /// Divide the bar into segments of `size` cells.
+    pub fn with_size(size: u16) -> Self {
+        Self {
+            size,
+            ..Default::default()
+        }
+    }
+
+    /// Start and end cell of each segment of a bar of `len` cells.
+    pub(crate) fn layout(&self, len: u16) -> Vec<(u16, u16)> {
+        let gap = self.gap as u32;
+        let len = len as u32;
+        match self.count {
+            Some(count) if count > 0 => {
+                let count = count as u32;
+                (0..count)
+                    .map(|index| {
+                        let start = index * (len + gap) / count;
+                        let end = ((index + 1) * (len + gap) / count).saturating_sub(gap);
+                        (start as u16, end.max(start + 1).min(len) as u16)
+                    })
+                    .filter(|(start, end)| start < end)
+                    .collect()
+            }
+            _ => {
+                let step = self.size.max(1) as u32 + gap;
+                (0..(len + gap) / step)
+                    .map(|index| {
+                        let start = index * step;
+                        (start as u16, (start + self.size.max(1) as u32) as u16)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// The values shown in the labels of a [`Meter`] with an [average](Meter::average_db) level
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LabelValue {
+    /// Show the peak level.
+    Peak,
+    /// Show the average level.
+    Average,
+    /// Show the peak and the average level.
+    #[default]
+    Both,
+}
+
+/// A level of a [`Meter`] channel, converted to a ratio with the scale of the meter when rendered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Level {
+    Db(f32),
+    Ratio(f32),
+}
+
+/// Identifies the overs reported with one call to [`Meter::overs`], so that rendering the same
+/// meter again does not count them twice.
+///
+/// Generations always compare equal, so that meters with the same settings and input compare
+/// equal.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct OversGeneration(pub(crate) u64);
+
+impl OversGeneration {
+    /// A generation that was not handed out before.
+    fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl PartialEq for OversGeneration {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+/// A colour zone of the [`Meter`] widget
+///
+/// A zone starts at `start_db` dBFS and reaches up to the start of the next zone. Bar cells in the
+/// zone are drawn with `style`. Levels below the lowest zone use the style of the lowest zone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorZone {
+    pub start_db: f32,
+    pub style: Style,
+}
+
+impl ColorZone {
+    /// Create a new [`ColorZone`] starting at `start_db` dBFS.
+    pub fn new(start_db: f32, style: impl Into<Style>) -> Self {
+        Self {
+            start_db,
+            style: style.into(),
+        }
+    }
+
+    /// The default zones: green, yellow from -12 dBFS and red from -3 dBFS.
+    pub fn default_zones() -> Vec<ColorZone> {
+        vec![
+            ColorZone::new(MIN_DB, Color::Green),
+            ColorZone::new(YELLOW_START_DB, Color::Yellow),
+            ColorZone::new(RED_START_DB, Color::Red),
+        ]
+    }
 }
 
 /// A widget to display an audio meter.
 ///
 /// A `Meter` renders a bar filled according to the value given to [`Meter::db`], [`Meter::sample_amplitude`] or
-/// [`Meter::ratio`]. The bar width and height are defined by the [`Rect`] it is
-/// [rendered](Widget::render) in.
+/// [`Meter::ratio`]. The bar width and height are defined by the [`Rect`](ratatui::layout::Rect)
+/// it is [rendered](ratatui::widgets::Widget::render) in.
+///
+/// By default the bars fill from left to right. Use [`Meter::direction`] with
+/// [`Direction::Vertical`] to render bars that fill from bottom to top, with the scale drawn as a
+/// column to the left of the bars and the labels above them.
+///
+/// The colour zones of the bars and the styles of the other parts of the meter can be changed with
+/// [`Meter::zones`], [`Meter::empty_style`], [`Meter::peak_style`], [`Meter::label_style`] and
+/// [`Meter::scale_style`]. For example, EBU digital zones:
+///
+/// ```
+/// use rataudio_meter::{ColorZone, Meter};
+/// use ratatui::style::Color;
+///
+/// let meter = Meter::stereo().zones([
+///     ColorZone::new(-60.0, Color::Green),
+///     ColorZone::new(-18.0, Color::Yellow),
+///     ColorZone::new(-9.0, Color::Red),
+/// ]);
+/// ```
+///
+/// The dB range of the meter is set with [`Meter::db_range`], and the curve the levels are
+/// mapped to the bars with is set with [`Meter::mapping`]. The default is -120 dBFS to 0 dBFS on a
+/// [`PowerLaw`](crate::PowerLaw) curve. For example, a meter with 6 dB headroom for float signals
+/// on a scale that is linear in dB:
+///
+/// ```
+/// use rataudio_meter::{LinearDb, Meter};
+///
+/// let meter = Meter::stereo().db_range(-60.0, 6.0).mapping(LinearDb);
+/// ```
+///
+/// Instead of discrete zones, the bars can also be coloured with a smooth [`Gradient`] using
+/// [`Meter::gradient`].
 ///
 /// [`Meter`] is also a [`StatefulWidget`], which means you can use it with [`MeterState`] to allow
 /// the meter to hold its peak value for a certain amount of time.
+///
+/// [`StatefulWidget`]: ratatui::widgets::StatefulWidget
+/// [`MeterState`]: crate::MeterState
 #[derive(Debug, Clone, PartialEq)]
 pub struct Meter<'a> {
     pub(crate) block: Option<Block<'a>>,
-    pub(crate) ratio: [f32; 2],
+    pub(crate) levels: Vec<Level>,
     pub(crate) channels: usize,
     pub(crate) show_labels: bool,
     pub(crate) show_scale: bool,
+    pub(crate) direction: Direction,
+    pub(crate) true_peak: Option<Vec<f32>>,
+    pub(crate) show_clip_indicator: bool,
+    pub(crate) clip_threshold: f32,
+    pub(crate) overs: Vec<usize>,
+    pub(crate) overs_generation: OversGeneration,
+    pub(crate) zones: Vec<ColorZone>,
+    pub(crate) empty_style: Style,
+    pub(crate) peak_style: Option<Style>,
+    pub(crate) label_style: Style,
+    pub(crate) scale_style: Style,
+    pub(crate) gradient: Option<Gradient>,
+    pub(crate) color_depth: Option<ColorDepth>,
+    pub(crate) scale: DbScale,
+    pub(crate) bar_mode: BarMode,
+    pub(crate) average: Option<Vec<Level>>,
+    pub(crate) peak_layer_style: Option<Style>,
+    pub(crate) label_value: LabelValue,
 }
 
 impl<'a> Meter<'a> {
@@ -37,26 +293,43 @@ impl<'a> Meter<'a> {
         self
     }
 
-    /// Create a new mono [`Meter`] widget.
-    pub fn mono() -> Self {
+    /// Create a new [`Meter`] widget with `channels` bars.
+    pub fn new(channels: usize) -> Self {
         Self {
             block: None,
-            ratio: [0.0; 2],
-            channels: 1,
+            levels: vec![Level::Ratio(0.0); channels],
+            channels,
             show_labels: true,
             show_scale: true,
+            direction: Direction::Horizontal,
+            true_peak: None,
+            show_clip_indicator: false,
+            clip_threshold: 0.0,
+            overs: vec![0; channels],
+            overs_generation: OversGeneration::default(),
+            zones: ColorZone::default_zones(),
+            empty_style: Style::default(),
+            peak_style: None,
+            label_style: Style::default(),
+            scale_style: Style::default(),
+            gradient: None,
+            color_depth: None,
+            scale: DbScale::default(),
+            bar_mode: BarMode::default(),
+            average: None,
+            peak_layer_style: None,
+            label_value: LabelValue::default(),
         }
     }
 
+    /// Create a new mono [`Meter`] widget.
+    pub fn mono() -> Self {
+        Self::new(1)
+    }
+
     /// Create a new stereo [`Meter`] widget.
     pub fn stereo() -> Self {
-        Self {
-            block: None,
-            ratio: [0.0; 2],
-            channels: 2,
-            show_labels: true,
-            show_scale: true,
-        }
+        Self::new(2)
     }
 
     /// Get the number of channels for this [`Meter`].
@@ -78,20 +351,165 @@ impl<'a> Meter<'a> {
         self
     }
 
+    /// Show or hide a clip indicator at the end of each bar.
+    ///
+    /// When the [`Meter`] is rendered as a stateful widget, the indicator latches when the level
+    /// of a channel reaches the [clip threshold](Meter::clip_threshold) or [overs](Meter::overs)
+    /// are reported, and stays lit until it is reset with [`MeterState::reset_clips`].
+    ///
+    /// [`MeterState::reset_clips`]: crate::MeterState::reset_clips
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn show_clip_indicator(mut self, show: bool) -> Self {
+        self.show_clip_indicator = show;
+        self
+    }
+
+    /// Set the level in dBFS at or above which the clip indicator latches. Defaults to 0.0dBFS.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn clip_threshold(mut self, db: f32) -> Self {
+        self.clip_threshold = db;
+        self
+    }
+
+    /// Report the number of overs per channel since the last frame, as counted by
+    /// [`LevelAnalyzer::overs`](crate::LevelAnalyzer::overs).
+    ///
+    /// Overs latch the clip indicator and are added up in the [`MeterState`](crate::MeterState)
+    /// once, however often the meter is rendered.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn overs(mut self, overs: &[usize]) -> Self {
+        for (channel, count) in self.overs.iter_mut().enumerate() {
+            *count = overs.get(channel).copied().unwrap_or(0);
+        }
+        self.overs_generation = OversGeneration::next();
+        self
+    }
+
+    /// Set the colour zones of the bars.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `zones` is empty.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn zones(mut self, zones: impl IntoIterator<Item = ColorZone>) -> Self {
+        let mut zones: Vec<ColorZone> = zones.into_iter().collect();
+        assert!(
+            !zones.is_empty(),
+            "There should be at least one color zone."
+        );
+        zones.sort_by(|a, b| a.start_db.total_cmp(&b.start_db));
+        self.zones = zones;
+        self
+    }
+
+    /// Colour each cell of the bars by its level on `gradient` instead of by colour zones.
+    ///
+    /// Only the foreground colour of the zone styles is replaced, other style attributes of the
+    /// zones still apply.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn gradient(mut self, gradient: Gradient) -> Self {
+        self.gradient = Some(gradient);
+        self
+    }
+
+    /// Set the colour depth the gradient is rendered with.
+    ///
+    /// Defaults to the depth [detected](ColorDepth::detect) from the environment the first time a
+    /// meter is rendered.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn color_depth(mut self, depth: ColorDepth) -> Self {
+        self.color_depth = Some(depth);
+        self
+    }
+
+    /// Set the style of the empty part of the bars.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn empty_style(mut self, style: impl Into<Style>) -> Self {
+        self.empty_style = style.into();
+        self
+    }
+
+    /// Set the style of the peak hold marker. Defaults to the style of the zone the marker is in.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn peak_style(mut self, style: impl Into<Style>) -> Self {
+        self.peak_style = Some(style.into());
+        self
+    }
+
+    /// Set the style of the decibel labels.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn label_style(mut self, style: impl Into<Style>) -> Self {
+        self.label_style = style.into();
+        self
+    }
+
+    /// Set the style of the scale.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn scale_style(mut self, style: impl Into<Style>) -> Self {
+        self.scale_style = style.into();
+        self
+    }
+
+    /// Set the range of the [`Meter`] in dBFS. Defaults to -120 dBFS to 0 dBFS.
+    ///
+    /// Levels at or below `floor_db` leave the bar empty and levels at or above `ceiling_db` fill
+    /// it. Use a ceiling above 0 dBFS to show the headroom of floating point signals.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `floor_db` is not below `ceiling_db`.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn db_range(mut self, floor_db: f32, ceiling_db: f32) -> Self {
+        assert!(
+            floor_db < ceiling_db,
+            "The floor should be below the ceiling."
+        );
+        self.scale.floor_db = floor_db;
+        self.scale.ceiling_db = ceiling_db;
+        self
+    }
+
+    /// Set the curve levels are mapped to the bars with. Defaults to [`PowerLaw`](crate::PowerLaw).
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn mapping(mut self, mapping: impl ScaleMapping + 'static) -> Self {
+        self.scale.mapping = Arc::new(mapping);
+        self
+    }
+
+    /// Set how the bars are drawn. Defaults to [`BarMode::Cells`].
+    ///
+    /// [`BarMode::Eighths`] gives the bars eight times the resolution of whole cells.
+    /// [`BarMode::Segments`] draws the bars as LED segments:
+    ///
+    /// ```
+    /// use rataudio_meter::{BarMode, Meter, Segments};
+    ///
+    /// let meter = Meter::stereo().bar_mode(BarMode::Segments(Segments::with_count(24)));
+    /// ```
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn bar_mode(mut self, mode: BarMode) -> Self {
+        self.bar_mode = mode;
+        self
+    }
+
+    /// Set the direction the bars of the [`Meter`] fill in.
+    ///
+    /// [`Direction::Horizontal`] (the default) renders one row per channel filling from left to
+    /// right. [`Direction::Vertical`] renders one column per channel filling from bottom to top.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
     /// Set the value of the [`Meter`] widget in decibels relative to full scale.
-    /// This method will saturate values above 0.0dBFS to max.
+    /// Values above the ceiling of the [range](Meter::db_range) saturate to max.
+    ///
+    /// Channels without a value in `input` are set to silence and values beyond the number of
+    /// channels of the meter are ignored.
     #[must_use = "method moves the value of self and returns the modified value"]
     pub fn db(mut self, input: MeterInput) -> Self {
-        match input {
-            MeterInput::Mono(dbfs) => {
-                self.ratio[0] = MeterScale::db_to_ratio(dbfs);
-                self.ratio[1] = 0.0;
-            }
-            MeterInput::Stereo(left_dbfs, right_dbfs) => {
-                self.ratio[0] = MeterScale::db_to_ratio(left_dbfs);
-                self.ratio[1] = MeterScale::db_to_ratio(right_dbfs);
-            }
-        }
+        self.set_levels(input, Level::Db);
         self
     }
 
@@ -99,25 +517,14 @@ impl<'a> Meter<'a> {
     /// This method will panic if the value of `sample` is not between 0.0 and 1.0 inclusively.
     #[must_use = "method moves the value of self and returns the modified value"]
     pub fn sample_amplitude(mut self, input: MeterInput) -> Self {
-        match input {
-            MeterInput::Mono(ampl) => {
-                assert!(
-                    (0.0..=1.0).contains(&ampl),
-                    "Ratio should be between 0 and 1 inclusively."
-                );
-                self.ratio[0] = MeterScale::sample_to_ratio(ampl);
-                self.ratio[1] = 0.0;
-            }
-            MeterInput::Stereo(left_ampl, right_ampl) => {
-                assert!(
-                    (0.0..=1.0).contains(&left_ampl) && (0.0..=1.0).contains(&right_ampl),
-                    "Ratio should be between 0 and 1 inclusively."
-                );
-                self.ratio[0] = MeterScale::sample_to_ratio(left_ampl);
-                self.ratio[1] = MeterScale::sample_to_ratio(right_ampl);
-            }
-        }
-
+        let values = input.into_values();
+        assert!(
+            values.iter().all(|ampl| (0.0..=1.0).contains(ampl)),
+            "Ratio should be between 0 and 1 inclusively."
+        );
+        self.set_levels(MeterInput::Channels(values), |ampl| {
+            Level::Db(MeterScale::sample_to_db(ampl))
+        });
         self
     }
 
@@ -130,62 +537,232 @@ impl<'a> Meter<'a> {
     /// This method will panic if the value of `ratio` is not between 0.0 and 1.0 inclusively.
     #[must_use = "method moves the value of self and returns the modified value"]
     pub fn ratio(mut self, input: MeterInput) -> Self {
-        match input {
-            MeterInput::Mono(ratio) => {
-                assert!(
-                    (0.0..=1.0).contains(&ratio),
-                    "Ratio should be between 0 and 1 inclusively."
-                );
-                self.ratio[0] = ratio;
-                self.ratio[1] = 0.0;
-            }
-            MeterInput::Stereo(left_ratio, right_ratio) => {
-                assert!(
-                    (0.0..=1.0).contains(&left_ratio) && (0.0..=1.0).contains(&right_ratio),
-                    "Ratio should be between 0 and 1 inclusively."
-                );
-                self.ratio[0] = left_ratio;
-                self.ratio[1] = right_ratio;
-            }
-        }
+        let values = input.into_values();
+        assert!(
+            values.iter().all(|ratio| (0.0..=1.0).contains(ratio)),
+            "Ratio should be between 0 and 1 inclusively."
+        );
+        self.set_levels(MeterInput::Channels(values), Level::Ratio);
+        self
+    }
+
+    /// Set the average level of the [`Meter`] widget in dBFS, such as the RMS level or loudness.
+    ///
+    /// The average level is drawn as a solid bar on top of the peak level set with [`Meter::db`],
+    /// which is drawn as a lighter layer. Both levels use the same scale. Unlike the peak level,
+    /// the average level is shown without [ballistics](crate::MeterState::ballistics).
+    ///
+    /// ```
+    /// use rataudio_meter::{LabelValue, Meter, MeterInput};
+    ///
+    /// let meter = Meter::stereo()
+    ///     .db(MeterInput::Stereo(-6.0, -8.0))
+    ///     .average_db(MeterInput::Stereo(-18.0, -20.0))
+    ///     .label_value(LabelValue::Both);
+    /// ```
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn average_db(mut self, input: MeterInput) -> Self {
+        let values = input.into_values();
+        self.average = Some(
+            (0..self.channels)
+                .map(|channel| Level::Db(values.get(channel).copied().unwrap_or(f32::NEG_INFINITY)))
+                .collect(),
+        );
         self
     }
+
+    /// Set the style of the peak layer drawn beyond the [average](Meter::average_db) level.
+    /// Defaults to the style of the zone the cell is in.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn peak_layer_style(mut self, style: impl Into<Style>) -> Self {
+        self.peak_layer_style = Some(style.into());
+        self
+    }
+
+    /// Set the values shown in the labels when an [average](Meter::average_db) level is set.
+    /// Defaults to [`LabelValue::Both`].
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn label_value(mut self, value: LabelValue) -> Self {
+        self.label_value = value;
+        self
+    }
+
+    /// Set the true-peak level of the [`Meter`] widget in dBTP.
+    ///
+    /// A marker is drawn at the true-peak level of each channel next to the sample-peak bar, and
+    /// the labels show the true-peak level. Levels above 0.0dBTP saturate the marker to max.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn true_peak(mut self, input: MeterInput) -> Self {
+        let values = input.into_values();
+        self.true_peak = Some(
+            (0..self.channels)
+                .map(|channel| values.get(channel).copied().unwrap_or(f32::NEG_INFINITY))
+                .collect(),
+        );
+        self
+    }
+
+    fn set_levels(&mut self, input: MeterInput, to_level: impl Fn(f32) -> Level) {
+        let values = input.into_values();
+        for (channel, level) in self.levels.iter_mut().enumerate() {
+            *level = values
+                .get(channel)
+                .copied()
+                .map_or(Level::Ratio(0.0), &to_level);
+        }
+    }
+
+    /// The ratio of each channel on the scale of the [`Meter`].
+    pub(crate) fn ratios(&self) -> Vec<f32> {
+        self.levels
+            .iter()
+            .map(|level| self.level_ratio(*level))
+            .collect()
+    }
+
+    /// The average ratio of each channel, if an average level is set.
+    pub(crate) fn average_ratios(&self) -> Option<Vec<f32>> {
+        let average = self.average.as_ref()?;
+        Some(
+            average
+                .iter()
+                .map(|level| self.level_ratio(*level))
+                .collect(),
+        )
+    }
+
+    /// The input level of `channel` in dBFS, negative infinity for a ratio of 0.0.
+    pub(crate) fn input_db(&self, channel: usize) -> f32 {
+        match self.levels[channel] {
+            Level::Db(db) => db,
+            Level::Ratio(ratio) if ratio <= 0.0 => f32::NEG_INFINITY,
+            Level::Ratio(ratio) => self.scale.ratio_to_db(ratio),
+        }
+    }
+
+    fn level_ratio(&self, level: Level) -> f32 {
+        match level {
+            Level::Db(db) => self.scale.db_to_ratio(db),
+            Level::Ratio(ratio) => ratio,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::scaling::LinearDb;
 
     #[test]
     fn meter_db_zero() {
         let meter = Meter::mono().db(MeterInput::Mono(0.0));
-        assert_eq!(meter.ratio[0], 1.0)
+        assert_eq!(meter.ratios()[0], 1.0)
     }
 
     #[test]
     fn meter_db_upper_bound() {
         let meter = Meter::mono().db(MeterInput::Mono(0.1));
-        assert_eq!(meter.ratio[0], 1.0)
+        assert_eq!(meter.ratios()[0], 1.0)
     }
 
     #[test]
     fn meter_db_lower_bound() {
         let meter = Meter::mono().db(MeterInput::Mono(-130.0));
-        assert_eq!(meter.ratio[0], 0.0);
+        assert_eq!(meter.ratios()[0], 0.0);
     }
 
     #[test]
     fn meter_stereo_db() {
         let meter = Meter::stereo().db(MeterInput::Stereo(0.0, 0.0));
-        assert_eq!(meter.ratio[0], 1.0);
-        assert_eq!(meter.ratio[1], 1.0);
+        assert_eq!(meter.ratios()[0], 1.0);
+        assert_eq!(meter.ratios()[1], 1.0);
     }
 
     #[test]
     fn meter_stereo_sample_amplitudes() {
         let meter = Meter::stereo().sample_amplitude(MeterInput::Stereo(1.0, 0.0));
-        assert_eq!(meter.ratio[0], 1.0);
-        assert_eq!(meter.ratio[1], 0.0);
+        assert_eq!(meter.ratios()[0], 1.0);
+        assert_eq!(meter.ratios()[1], 0.0);
+    }
+
+    #[test]
+    fn meter_multichannel_db() {
+        let meter = Meter::new(6).db(MeterInput::from(&[0.0, -130.0, 0.0][..]));
+        assert_eq!(meter.channels(), 6);
+        assert_eq!(meter.ratios(), vec![1.0, 0.0, 1.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn meter_input_from_iterator() {
+        let input: MeterInput = (0..4).map(|_| 0.5).collect();
+        let meter = Meter::new(4).ratio(input);
+        assert_eq!(meter.ratios(), vec![0.5; 4]);
+    }
+
+    #[test]
+    fn meter_db_range_applies_regardless_of_order() {
+        let meter = Meter::mono()
+            .db(MeterInput::Mono(-30.0))
+            .db_range(-60.0, 0.0)
+            .mapping(LinearDb);
+        assert_eq!(meter.ratios(), vec![0.5]);
+
+        let meter = Meter::mono().db_range(-60.0, 6.0).db(MeterInput::Mono(3.0));
+        assert!(meter.ratios()[0] < 1.0);
+    }
+
+    #[test]
+    fn default_meters_compare_equal() {
+        assert_eq!(Meter::mono(), Meter::mono());
+        assert_eq!(
+            Meter::stereo().mapping(LinearDb),
+            Meter::stereo().mapping(LinearDb)
+        );
+        assert_ne!(Meter::stereo(), Meter::stereo().mapping(LinearDb));
+    }
+
+    #[test]
+    #[should_panic = "The floor should be below the ceiling"]
+    fn meter_invalid_db_range() {
+        let _ = Meter::mono().db_range(0.0, -60.0);
+    }
+
+    #[test]
+    fn segments_by_count_fill_the_bar() {
+        let segments = Segments::with_count(4);
+        assert_eq!(segments.layout(11), vec![(0, 2), (3, 5), (6, 8), (9, 11)]);
+    }
+
+    #[test]
+    fn segments_by_size() {
+        let segments = Segments {
+            gap: 2,
+            ..Segments::with_size(3)
+        };
+        assert_eq!(segments.layout(14), vec![(0, 3), (5, 8), (10, 13)]);
+    }
+
+    #[test]
+    fn meter_true_peak() {
+        let meter = Meter::stereo().true_peak(MeterInput::Mono(0.5));
+        assert_eq!(meter.true_peak, Some(vec![0.5, f32::NEG_INFINITY]));
+    }
+
+    #[test]
+    fn meter_zones_are_sorted() {
+        let meter = Meter::mono().zones([
+            ColorZone::new(-9.0, Color::Red),
+            ColorZone::new(-60.0, Color::Green),
+            ColorZone::new(-18.0, Color::Yellow),
+        ]);
+        let starts: Vec<f32> = meter.zones.iter().map(|zone| zone.start_db).collect();
+        assert_eq!(starts, vec![-60.0, -18.0, -9.0]);
+    }
+
+    #[test]
+    #[should_panic = "There should be at least one color zone"]
+    fn meter_without_zones() {
+        let _ = Meter::mono().zones([]);
     }
 
     #[test]
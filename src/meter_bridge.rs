@@ -0,0 +1,609 @@
+//! The [`MeterBridge`] widget lays out many named [`Meter`] strips with a shared scale.
+
+use std::sync::Arc;
+
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout},
+    prelude::{BlockExt, Buffer, Rect, Style, Widget},
+    text::Line,
+    widgets::{Block, Paragraph, StatefulWidget},
+};
+
+use crate::{
+    meter::Meter,
+    rendering::VERTICAL_SCALE_WIDTH,
+    scaling::{DbScale, ScaleMapping},
+    state::MeterState,
+};
+
+/// A named [`Meter`] in a [`MeterBridge`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeterStrip<'a> {
+    pub(crate) name: Line<'a>,
+    pub(crate) meter: Meter<'a>,
+}
+
+impl<'a> MeterStrip<'a> {
+    /// Create a new strip showing `meter` under `name`.
+    pub fn new(name: impl Into<Line<'a>>, meter: Meter<'a>) -> Self {
+        Self {
+            name: name.into(),
+            meter,
+        }
+    }
+}
+
+/// State of the [`MeterBridge`] widget
+///
+/// The state holds one [`MeterState`] per strip, in the order of the strips:
+/// - [`strips`](MeterBridgeState::strips): the peak hold, ballistics and clip state of each strip
+///
+/// Missing states are added with the default [`MeterState`] when the bridge is rendered. To give
+/// the strips other ballistics or peak hold settings, fill
+/// [`strips`](MeterBridgeState::strips) before the first render.
+#[derive(Debug, Default, Clone)]
+pub struct MeterBridgeState {
+    pub strips: Vec<MeterState>,
+}
+
+impl MeterBridgeState {
+    /// Reset the clip indicators and over counts of all strips.
+    pub fn reset_clips(&mut self) {
+        self.strips.iter_mut().for_each(MeterState::reset_clips);
+    }
+
+    /// Reset the held peaks of all strips.
+    pub fn reset_peaks(&mut self) {
+        self.strips.iter_mut().for_each(MeterState::reset_peaks);
+    }
+}
+
+/// A widget to display many named meters side by side, like the meter bridge of a mixing console.
+///
+/// Each [`MeterStrip`] is a [`Meter`] with a name. By default the strips are vertical meters
+/// placed side by side with their names above them. With [`Direction::Horizontal`] the strips are
+/// stacked with their names to the left.
+///
+/// All strips share one dB range and mapping, set with [`MeterBridge::db_range`] and
+/// [`MeterBridge::mapping`], and the bridge draws the scale once per row or column of strips
+/// instead of once per meter. The strips are also given the direction of the bridge. Their other
+/// settings, like zones, labels or bar modes, are kept. The strips of each row or column are
+/// moved so their bars line up with each other and with the scale.
+///
+/// When there are too many strips for the area, they wrap onto more rows of strips no narrower
+/// than [`MeterBridge::min_strip_width`], or onto more columns of stacked strips whose bars are
+/// no shorter than it. Stacked strips that do not fit in the columns are not drawn.
+///
+/// ```
+/// use rataudio_meter::{Meter, MeterBridge, MeterInput, MeterStrip};
+///
+/// let bridge = MeterBridge::new([
+///     MeterStrip::new("Kick", Meter::mono().db(MeterInput::Mono(-6.0))),
+///     MeterStrip::new("OH", Meter::stereo().db(MeterInput::Stereo(-18.0, -20.0))),
+///     MeterStrip::new("Vox", Meter::mono().db(MeterInput::Mono(-12.0))),
+/// ])
+/// .db_range(-60.0, 6.0);
+/// ```
+///
+/// [`MeterBridge`] is a [`StatefulWidget`] with a [`MeterBridgeState`] that keeps a [`MeterState`]
+/// per strip.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeterBridge<'a> {
+    pub(crate) block: Option<Block<'a>>,
+    pub(crate) strips: Vec<MeterStrip<'a>>,
+    pub(crate) direction: Direction,
+    pub(crate) scale: DbScale,
+    pub(crate) show_scale: bool,
+    pub(crate) scale_style: Style,
+    pub(crate) name_style: Style,
+    pub(crate) min_strip_width: u16,
+    pub(crate) spacing: u16,
+}
+
+impl<'a> MeterBridge<'a> {
+    /// Create a new [`MeterBridge`] widget with `strips`.
+    pub fn new(strips: impl IntoIterator<Item = MeterStrip<'a>>) -> Self {
+        let bridge = Self {
+            block: None,
+            strips: strips.into_iter().collect(),
+            direction: Direction::Vertical,
+            scale: DbScale::default(),
+            show_scale: true,
+            scale_style: Style::default(),
+            name_style: Style::default(),
+            min_strip_width: 4,
+            spacing: 1,
+        };
+        bridge.sync_strips()
+    }
+
+    /// Give every strip the direction and scale of the bridge.
+    fn sync_strips(mut self) -> Self {
+        for strip in &mut self.strips {
+            strip.meter.direction = self.direction;
+            strip.meter.scale = self.scale.clone();
+            strip.meter.show_scale = false;
+        }
+        self
+    }
+
+    /// Surrounds the `MeterBridge` with a [`Block`].
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    /// Set the direction the bars of the strips fill in. Defaults to [`Direction::Vertical`].
+    ///
+    /// Vertical strips are placed side by side, horizontal strips are stacked.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self.sync_strips()
+    }
+
+    /// Set the dB range of all strips. Defaults to -120 dBFS to 0 dBFS.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `floor_db` is not below `ceiling_db`.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn db_range(mut self, floor_db: f32, ceiling_db: f32) -> Self {
+        assert!(
+            floor_db < ceiling_db,
+            "The floor should be below the ceiling."
+        );
+        self.scale.floor_db = floor_db;
+        self.scale.ceiling_db = ceiling_db;
+        self.sync_strips()
+    }
+
+    /// Set the curve levels are mapped to the bars of all strips with. Defaults to
+    /// [`PowerLaw`](crate::PowerLaw).
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn mapping(mut self, mapping: impl ScaleMapping + 'static) -> Self {
+        self.scale.mapping = Arc::new(mapping);
+        self.sync_strips()
+    }
+
+    /// Show or hide the shared scale of the [`MeterBridge`].
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn show_scale(mut self, show: bool) -> Self {
+        self.show_scale = show;
+        self
+    }
+
+    /// Set the style of the shared scale.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn scale_style(mut self, style: impl Into<Style>) -> Self {
+        self.scale_style = style.into();
+        self
+    }
+
+    /// Set the style of the strip names.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn name_style(mut self, style: impl Into<Style>) -> Self {
+        self.name_style = style.into();
+        self
+    }
+
+    /// Set the narrowest a vertical strip can be before the strips wrap onto another row, and the
+    /// shortest the bars of horizontal strips can be before they stop wrapping onto another
+    /// column. Defaults to 4 cells.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `width` is zero.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn min_strip_width(mut self, width: u16) -> Self {
+        assert!(width > 0, "Strip width should be at least 1 cell.");
+        self.min_strip_width = width;
+        self
+    }
+
+    /// Set the number of empty cells between two strips. Defaults to 1.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn spacing(mut self, spacing: u16) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Get the number of strips of this [`MeterBridge`].
+    pub fn strips(&self) -> usize {
+        self.strips.len()
+    }
+
+    /// A meter that draws the shared scale.
+    fn scale_meter(&self) -> Meter<'a> {
+        let mut meter = Meter::mono().direction(self.direction);
+        meter.scale = self.scale.clone();
+        meter.scale_style = self.scale_style;
+        meter
+    }
+
+    /// Strips side by side in rows, with the names above the bars and the scale to the left.
+    fn render_vertical(&self, area: Rect, buf: &mut Buffer, state: &mut MeterBridgeState) {
+        let scale_width = if self.show_scale {
+            VERTICAL_SCALE_WIDTH + self.spacing
+        } else {
+            0
+        };
+        let available = area.width.saturating_sub(scale_width);
+        let per_row = ((available + self.spacing) / (self.min_strip_width + self.spacing)).max(1);
+        let per_row = (per_row as usize).min(self.strips.len());
+        let rows = self.strips.len().div_ceil(per_row);
+        let row_areas = Layout::vertical(vec![Constraint::Fill(1); rows]).split(area);
+        let name_split = Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]);
+
+        for (row, row_area) in row_areas.iter().enumerate() {
+            let mut constraints = Vec::new();
+            if self.show_scale {
+                constraints.push(Constraint::Length(VERTICAL_SCALE_WIDTH));
+            }
+            constraints.extend(vec![Constraint::Fill(1); per_row]);
+            let columns = Layout::horizontal(constraints)
+                .spacing(self.spacing)
+                .split(*row_area);
+            let strip_columns = &columns[self.show_scale as usize..];
+
+            let first = row * per_row;
+            let last = (first + per_row).min(self.strips.len());
+            let strips = &self.strips[first..last];
+            let (name_areas, meter_areas): (Vec<Rect>, Vec<Rect>) = strip_columns
+                .iter()
+                .map(|column| {
+                    let [name_area, meter_area] = name_split.areas(*column);
+                    (name_area, meter_area)
+                })
+                .unzip();
+            let (meter_areas, before, after) = self.align_bars(strips, &meter_areas);
+            for (index, strip) in strips.iter().enumerate() {
+                Paragraph::new(strip.name.clone())
+                    .style(self.name_style)
+                    .alignment(Alignment::Center)
+                    .render(name_areas[index], buf);
+                StatefulWidget::render(
+                    &strip.meter,
+                    meter_areas[index],
+                    buf,
+                    &mut state.strips[first + index],
+                );
+            }
+
+            if self.show_scale {
+                let [_, scale_area] = name_split.areas(columns[0]);
+                let scale_area = self.inset(scale_area, before, after);
+                self.scale_meter().render_meter_scale(scale_area, buf);
+            }
+        }
+    }
+
+    /// Strips stacked in columns, with the names to the left of the bars and the scale below.
+    fn render_horizontal(&self, area: Rect, buf: &mut Buffer, state: &mut MeterBridgeState) {
+        let name_width = self
+            .strips
+            .iter()
+            .map(|strip| strip.name.width() as u16)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let strip_height = self
+            .strips
+            .iter()
+            .map(|strip| strip.meter.channels as u16 * (1 + strip.meter.show_labels as u16))
+            .max()
+            .unwrap_or(1)
+            .max(1);
+        // The scale row is spaced from the strips like another strip
+        let scale_height = if self.show_scale { 1 + self.spacing } else { 0 };
+        let available = area.height.saturating_sub(scale_height);
+        let per_column = ((available + self.spacing) / (strip_height + self.spacing)).max(1);
+        // Only add columns while their bars stay at least the minimum strip width
+        let column_width = name_width + self.min_strip_width;
+        let max_columns = ((area.width + self.spacing) / (column_width + self.spacing)).max(1);
+        let per_column = (per_column as usize).min(self.strips.len());
+        let columns = self
+            .strips
+            .len()
+            .div_ceil(per_column)
+            .min(max_columns as usize);
+        let column_areas = Layout::horizontal(vec![Constraint::Fill(1); columns])
+            .spacing(self.spacing)
+            .split(area);
+        let name_split = Layout::horizontal([Constraint::Length(name_width), Constraint::Fill(1)]);
+
+        for (column, column_area) in column_areas.iter().enumerate() {
+            let mut constraints = vec![Constraint::Length(strip_height); per_column];
+            if self.show_scale {
+                constraints.push(Constraint::Length(1));
+            }
+            let rows = Layout::vertical(constraints)
+                .spacing(self.spacing)
+                .split(*column_area);
+
+            let first = column * per_column;
+            let last = (first + per_column).min(self.strips.len());
+            let strips = &self.strips[first..last];
+            let (name_areas, meter_areas): (Vec<Rect>, Vec<Rect>) = rows[..per_column]
+                .iter()
+                .map(|row| {
+                    let [name_area, meter_area] = name_split.areas(*row);
+                    (name_area, meter_area)
+                })
+                .unzip();
+            let (meter_areas, before, after) = self.align_bars(strips, &meter_areas);
+            for (index, strip) in strips.iter().enumerate() {
+                Paragraph::new(strip.name.clone())
+                    .style(self.name_style)
+                    .render(name_areas[index], buf);
+                StatefulWidget::render(
+                    &strip.meter,
+                    meter_areas[index],
+                    buf,
+                    &mut state.strips[first + index],
+                );
+            }
+
+            if self.show_scale {
+                let [_, scale_area] = name_split.areas(rows[per_column]);
+                let scale_area = self.inset(scale_area, before, after);
+                self.scale_meter().render_meter_scale(scale_area, buf);
+            }
+        }
+    }
+
+    /// Line up the bars of `strips` rendered in `areas`.
+    ///
+    /// Each area is shrunk along the bars so that the labels and clip indicators of every strip
+    /// take the same cells. Returns the shrunk areas and the number of cells before and after the
+    /// bars, which are the same for every strip.
+    fn align_bars(&self, strips: &[MeterStrip], areas: &[Rect]) -> (Vec<Rect>, u16, u16) {
+        let margins: Vec<(u16, u16)> = strips
+            .iter()
+            .zip(areas)
+            .map(|(strip, area)| {
+                let bar = strip.meter.areas(*area).0.meters[0];
+                match self.direction {
+                    Direction::Horizontal => (
+                        bar.left().saturating_sub(area.left()),
+                        area.right().saturating_sub(bar.right()),
+                    ),
+                    Direction::Vertical => (
+                        bar.top().saturating_sub(area.top()),
+                        area.bottom().saturating_sub(bar.bottom()),
+                    ),
+                }
+            })
+            .collect();
+        let before = margins.iter().map(|margin| margin.0).max().unwrap_or(0);
+        let after = margins.iter().map(|margin| margin.1).max().unwrap_or(0);
+        let aligned = areas
+            .iter()
+            .zip(&margins)
+            .map(|(area, margin)| self.inset(*area, before - margin.0, after - margin.1))
+            .collect();
+        (aligned, before, after)
+    }
+
+    /// Shrink `area` by `before` cells at the start and `after` cells at the end along the bars.
+    fn inset(&self, area: Rect, before: u16, after: u16) -> Rect {
+        match self.direction {
+            Direction::Horizontal => Rect {
+                x: area.x + before.min(area.width),
+                width: area.width.saturating_sub(before.saturating_add(after)),
+                ..area
+            },
+            Direction::Vertical => Rect {
+                y: area.y + before.min(area.height),
+                height: area.height.saturating_sub(before.saturating_add(after)),
+                ..area
+            },
+        }
+    }
+}
+
+impl Widget for MeterBridge<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Widget::render(&self, area, buf);
+    }
+}
+
+impl Widget for &MeterBridge<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut state = MeterBridgeState::default();
+        StatefulWidget::render(self, area, buf, &mut state);
+    }
+}
+
+impl StatefulWidget for MeterBridge<'_> {
+    type State = MeterBridgeState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        StatefulWidget::render(&self, area, buf, state);
+    }
+}
+
+impl StatefulWidget for &MeterBridge<'_> {
+    type State = MeterBridgeState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        if let Some(block) = self.block.as_ref() {
+            block.render(area, buf);
+        }
+
+        let bridge_area = self.block.inner_if_some(area);
+        if bridge_area.is_empty() || self.strips.is_empty() {
+            return;
+        }
+        state
+            .strips
+            .resize_with(self.strips.len(), MeterState::default);
+
+        match self.direction {
+            Direction::Horizontal => self.render_horizontal(bridge_area, buf, state),
+            Direction::Vertical => self.render_vertical(bridge_area, buf, state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::Color;
+
+    use super::*;
+    use crate::{meter::MeterInput, scaling::LinearDb};
+
+    fn strips(count: usize) -> Vec<MeterStrip<'static>> {
+        (0..count)
+            .map(|index| {
+                MeterStrip::new(
+                    format!("Ch{}", index + 1),
+                    Meter::mono().show_labels(false).db(MeterInput::Mono(0.0)),
+                )
+            })
+            .collect()
+    }
+
+    fn row(buf: &Buffer, y: u16) -> String {
+        (buf.area.left()..buf.area.right())
+            .map(|x| buf[(x, y)].symbol())
+            .collect()
+    }
+
+    #[test]
+    fn vertical_strips_share_one_scale() {
+        let bridge = MeterBridge::new([
+            MeterStrip::new(
+                "Kick",
+                Meter::mono().show_labels(false).db(MeterInput::Mono(0.0)),
+            ),
+            MeterStrip::new(
+                "Vox",
+                Meter::mono()
+                    .show_labels(false)
+                    .db(MeterInput::Mono(-120.0)),
+            ),
+        ])
+        .db_range(-60.0, 0.0)
+        .mapping(LinearDb);
+        let area = Rect::new(0, 0, 14, 8);
+        let mut buf = Buffer::empty(area);
+        let mut state = MeterBridgeState::default();
+        StatefulWidget::render(&bridge, area, &mut buf, &mut state);
+
+        assert_eq!(state.strips.len(), 2);
+        assert_eq!(row(&buf, 0), "     Kick  Vox");
+        assert!(row(&buf, 1).starts_with("   0"));
+        assert!(row(&buf, 7).starts_with(" -60"));
+        // The full strip reaches the top and the silent strip stays empty
+        assert_eq!(buf[(6, 1)].fg, Color::Red);
+        assert_eq!(buf[(11, 1)].symbol(), " ");
+        assert_eq!(state.strips[0].peak_db, vec![0.0]);
+    }
+
+    #[test]
+    fn vertical_strips_wrap_onto_rows() {
+        let bridge = MeterBridge::new(strips(5)).show_scale(false);
+        let area = Rect::new(0, 0, 15, 10);
+        let mut buf = Buffer::empty(area);
+        let mut state = MeterBridgeState::default();
+        StatefulWidget::render(&bridge, area, &mut buf, &mut state);
+
+        assert_eq!(row(&buf, 0), " Ch1  Ch2   Ch3");
+        assert_eq!(row(&buf, 5), " Ch4  Ch5      ");
+        assert_eq!(state.strips.len(), 5);
+        assert!(state.strips.iter().all(|strip| strip.peak_db == vec![0.0]));
+    }
+
+    #[test]
+    fn horizontal_strips_stack_into_columns() {
+        let bridge = MeterBridge::new(strips(3))
+            .direction(Direction::Horizontal)
+            .spacing(0)
+            .name_style(Color::Cyan);
+        let area = Rect::new(0, 0, 30, 3);
+        let mut buf = Buffer::empty(area);
+        Widget::render(&bridge, area, &mut buf);
+
+        assert!(row(&buf, 0).starts_with("Ch1 "));
+        assert!(row(&buf, 1).starts_with("Ch2 "));
+        assert!(row(&buf, 0)
+            .chars()
+            .skip(15)
+            .collect::<String>()
+            .starts_with("Ch3 "));
+        assert_eq!(buf[(0, 0)].fg, Color::Cyan);
+        // The scale under each column starts after the names
+        assert!(row(&buf, 2).starts_with("    -∞"));
+        assert_eq!(buf[(4, 0)].fg, Color::Green);
+        assert_eq!(buf[(14, 0)].fg, Color::Red);
+    }
+
+    #[test]
+    fn bars_of_different_strips_line_up_with_the_scale() {
+        let bridge = MeterBridge::new([
+            MeterStrip::new(
+                "A",
+                Meter::mono()
+                    .show_clip_indicator(true)
+                    .db(MeterInput::Mono(0.0)),
+            ),
+            MeterStrip::new(
+                "B",
+                Meter::mono()
+                    .show_labels(false)
+                    .show_clip_indicator(false)
+                    .db(MeterInput::Mono(0.0)),
+            ),
+        ])
+        .db_range(-60.0, 0.0)
+        .mapping(LinearDb);
+        let area = Rect::new(0, 0, 14, 10);
+        let mut buf = Buffer::empty(area);
+        Widget::render(&bridge, area, &mut buf);
+
+        // Below the name, the label and the clip indicator of the first strip
+        let top = |x: u16| (0..10).find(|&y| buf[(x, y)].symbol() == "▉");
+        assert_eq!(top(6), Some(4));
+        assert_eq!(top(11), Some(4));
+        assert!(row(&buf, 4).starts_with("   0"));
+        assert!(row(&buf, 9).starts_with(" -60"));
+    }
+
+    #[test]
+    fn horizontal_strips_keep_their_minimum_width() {
+        let bridge = MeterBridge::new(strips(8))
+            .direction(Direction::Horizontal)
+            .show_scale(false);
+        let area = Rect::new(0, 0, 30, 3);
+        let mut buf = Buffer::empty(area);
+        Widget::render(&bridge, area, &mut buf);
+
+        // Three columns of a 4 cell name and bars of at least 4 cells, 1 cell apart
+        assert_eq!(row(&buf, 0), "Ch1 ▉▉▉▉▉ Ch3 ▉▉▉▉▉▉ Ch5 ▉▉▉▉▉");
+        assert_eq!(row(&buf, 2), "Ch2 ▉▉▉▉▉ Ch4 ▉▉▉▉▉▉ Ch6 ▉▉▉▉▉");
+    }
+
+    #[test]
+    fn state_keeps_existing_strip_settings() {
+        let bridge = MeterBridge::new(strips(2));
+        let mut state = MeterBridgeState {
+            strips: vec![MeterState {
+                peak_hold_time: std::time::Duration::from_secs(5),
+                ..Default::default()
+            }],
+        };
+        let area = Rect::new(0, 0, 20, 10);
+        let mut buf = Buffer::empty(area);
+        StatefulWidget::render(&bridge, area, &mut buf, &mut state);
+
+        assert_eq!(state.strips.len(), 2);
+        assert_eq!(
+            state.strips[0].peak_hold_time,
+            std::time::Duration::from_secs(5)
+        );
+        state.reset_peaks();
+        assert!(state.strips.iter().all(|strip| strip.peak_db[0] < -100.0));
+    }
+}
@@ -0,0 +1,440 @@
+//! Reception of levels sent over UDP by a remote audio engine.
+//!
+//! A [`LevelReceiver`] listens on a UDP socket for level messages and maps them to meter
+//! channels, so a [`Meter`](crate::Meter) can show the levels of another process or host.
+
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use crate::{meter::MeterInput, scaling::MeterScale};
+
+/// Largest datagram the receiver reads.
+const MAX_DATAGRAM: usize = 65_536;
+
+/// Most datagrams read by one call to [`LevelReceiver::poll`].
+const MAX_DATAGRAMS_PER_POLL: usize = 256;
+
+/// Maximum depth of nested OSC bundles.
+const MAX_BUNDLE_DEPTH: usize = 8;
+
+/// Unit of the values in received level messages
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LevelUnit {
+    /// Levels in dBFS.
+    #[default]
+    Db,
+    /// Linear sample amplitudes between 0.0 and 1.0.
+    Amplitude,
+}
+
+/// A receiver of levels sent as UDP datagrams.
+///
+/// Two kinds of datagrams are understood, both numbering the remote channels from 0:
+/// - OSC messages with the address `<prefix>/<channel>` and one numeric argument, like
+///   `/meter/0 ,f -12.5`. A message to `<prefix>` itself sets one channel per argument. Numeric
+///   arguments can be `f`, `d` or `i`, and messages may be grouped in OSC bundles.
+/// - Text lines of the form `<channel> <level>`, which is handy for testing with `netcat`.
+///
+/// Remote channels are mapped one to one to meter channels unless routes are set with
+/// [`LevelReceiver::route`]. A channel that has not received a level for the
+/// [timeout](LevelReceiver::timeout) falls to silence, so a meter does not freeze on the last
+/// level when the sender stops.
+///
+/// The socket is non-blocking. Call [`LevelReceiver::poll`] once per frame to read all pending
+/// datagrams, then pass [`LevelReceiver::db`] to the meter:
+///
+/// ```no_run
+/// use rataudio_meter::{LevelReceiver, Meter};
+///
+/// let mut receiver = LevelReceiver::bind("0.0.0.0:9000", 2)?.route(4, 0).route(5, 1);
+/// // Once per frame
+/// receiver.poll()?;
+/// let meter = Meter::stereo().db(receiver.db());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct LevelReceiver {
+    socket: UdpSocket,
+    prefix: String,
+    unit: LevelUnit,
+    timeout: Duration,
+    routes: Vec<(usize, usize)>,
+    levels_db: Vec<f32>,
+    last_seen: Vec<Option<Instant>>,
+    buffer: Vec<u8>,
+}
+
+impl LevelReceiver {
+    /// Bind a receiver for `channels` meter channels to `addr`.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `channels` is zero.
+    pub fn bind(addr: impl ToSocketAddrs, channels: usize) -> io::Result<Self> {
+        assert!(channels > 0, "Channel count should be at least 1.");
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            prefix: "/meter".to_string(),
+            unit: LevelUnit::default(),
+            timeout: Duration::from_millis(500),
+            routes: Vec::new(),
+            levels_db: vec![f32::NEG_INFINITY; channels],
+            last_seen: vec![None; channels],
+            buffer: vec![0; MAX_DATAGRAM],
+        })
+    }
+
+    /// Set the OSC address prefix of level messages. Defaults to `/meter`.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Set the unit of the received levels. Defaults to [`LevelUnit::Db`].
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn unit(mut self, unit: LevelUnit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Set the time without messages after which a channel falls to silence. Defaults to 500ms.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Show the levels of remote channel `source` on meter channel `channel`.
+    ///
+    /// Once a route is set, remote channels without a route are ignored. Several remote channels
+    /// can be routed to the same meter channel, which then shows the highest of their levels.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `channel` is not below the channel count.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn route(mut self, source: usize, channel: usize) -> Self {
+        assert!(
+            channel < self.channels(),
+            "Channel should be below the channel count."
+        );
+        self.routes.push((source, channel));
+        self
+    }
+
+    /// Get the number of meter channels of this [`LevelReceiver`].
+    pub fn channels(&self) -> usize {
+        self.levels_db.len()
+    }
+
+    /// Get the local address the receiver is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Read the pending datagrams without blocking and return the number of levels received.
+    ///
+    /// A channel that receives several levels in one call keeps the highest, so short peaks
+    /// between two frames are not lost. At most 256 datagrams are read per call, so a flooding
+    /// sender can not stall the frame. The rest are read by the next call.
+    pub fn poll(&mut self) -> io::Result<usize> {
+        self.poll_at(Instant::now(), MAX_DATAGRAMS_PER_POLL)
+    }
+
+    fn poll_at(&mut self, now: Instant, max_datagrams: usize) -> io::Result<usize> {
+        let mut updates = Vec::new();
+        let mut datagrams = 0;
+        while datagrams < max_datagrams {
+            let length = match self.socket.recv(&mut self.buffer) {
+                Ok(length) => length,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            datagrams += 1;
+            let datagram = &self.buffer[..length];
+            if datagram.starts_with(b"/") || datagram.starts_with(b"#bundle\0") {
+                parse_osc(datagram, &self.prefix, 0, &mut updates);
+            } else if let Ok(text) = std::str::from_utf8(datagram) {
+                parse_text(text, &mut updates);
+            }
+        }
+
+        let mut received = 0;
+        let mut updated = vec![false; self.channels()];
+        for (source, level) in updates {
+            let db = match self.unit {
+                LevelUnit::Db => level,
+                LevelUnit::Amplitude => MeterScale::sample_to_db(level.abs()),
+            };
+            if db.is_nan() {
+                continue;
+            }
+            for channel in route_of(&self.routes, self.levels_db.len(), source) {
+                let level_db = &mut self.levels_db[channel];
+                *level_db = if updated[channel] {
+                    level_db.max(db)
+                } else {
+                    db
+                };
+                updated[channel] = true;
+                self.last_seen[channel] = Some(now);
+                received += 1;
+            }
+        }
+        Ok(received)
+    }
+
+    /// Level per channel in dBFS, ready to be passed to [`Meter::db`](crate::Meter::db).
+    ///
+    /// Channels that have not received a level within the timeout are silent.
+    pub fn db(&self) -> MeterInput {
+        self.db_at(Instant::now())
+    }
+
+    fn db_at(&self, now: Instant) -> MeterInput {
+        self.levels_db
+            .iter()
+            .zip(&self.last_seen)
+            .map(|(&db, last_seen)| match last_seen {
+                Some(time) if now.saturating_duration_since(*time) <= self.timeout => db,
+                _ => f32::NEG_INFINITY,
+            })
+            .collect()
+    }
+}
+
+/// Meter channels that show remote channel `source`, with `routes` to `channels` meter channels.
+fn route_of(
+    routes: &[(usize, usize)],
+    channels: usize,
+    source: usize,
+) -> impl Iterator<Item = usize> + '_ {
+    let direct = (routes.is_empty() && source < channels).then_some(source);
+    let routed = routes
+        .iter()
+        .filter(move |(from, _)| *from == source)
+        .map(|(_, to)| *to);
+    direct.into_iter().chain(routed)
+}
+
+/// Parse text lines of `<channel> <level>` into `updates`, skipping malformed lines.
+fn parse_text(text: &str, updates: &mut Vec<(usize, f32)>) {
+    for line in text.lines() {
+        let mut fields = line.split_whitespace();
+        if let (Some(channel), Some(level), None) = (fields.next(), fields.next(), fields.next()) {
+            if let (Ok(channel), Ok(level)) = (channel.parse(), level.parse()) {
+                updates.push((channel, level));
+            }
+        }
+    }
+}
+
+/// Parse an OSC packet into `updates`. Malformed packets are skipped.
+fn parse_osc(packet: &[u8], prefix: &str, depth: usize, updates: &mut Vec<(usize, f32)>) {
+    if let Some(mut rest) = packet.strip_prefix(b"#bundle\0") {
+        if depth >= MAX_BUNDLE_DEPTH || rest.len() < 8 {
+            return;
+        }
+        // Skip the time tag, levels are applied when they arrive
+        rest = &rest[8..];
+        while rest.len() >= 4 {
+            let size = i32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]);
+            let Some(element) = usize::try_from(size)
+                .ok()
+                .and_then(|size| rest.get(4..4 + size))
+            else {
+                return;
+            };
+            parse_osc(element, prefix, depth + 1, updates);
+            rest = &rest[4 + element.len()..];
+        }
+        return;
+    }
+
+    let Some((address, rest)) = osc_string(packet) else {
+        return;
+    };
+    let Some((tags, mut args)) = osc_string(rest) else {
+        return;
+    };
+    let Some(tags) = tags.strip_prefix(',') else {
+        return;
+    };
+    let mut values = Vec::new();
+    for tag in tags.chars() {
+        let value = match (tag, args.len()) {
+            ('f', 4..) => f32::from_be_bytes([args[0], args[1], args[2], args[3]]),
+            ('i', 4..) => i32::from_be_bytes([args[0], args[1], args[2], args[3]]) as f32,
+            ('d', 8..) => f64::from_be_bytes(args[..8].try_into().unwrap()) as f32,
+            _ => return,
+        };
+        args = &args[if tag == 'd' { 8 } else { 4 }..];
+        values.push(value);
+    }
+
+    let Some(channel) = address.strip_prefix(prefix) else {
+        return;
+    };
+    if channel.is_empty() {
+        updates.extend(values.into_iter().enumerate());
+    } else if let (Some(Ok(channel)), [value]) =
+        (channel.strip_prefix('/').map(str::parse), values.as_slice())
+    {
+        updates.push((channel, *value));
+    }
+}
+
+/// Split a null terminated OSC string padded to 4 bytes off the start of `bytes`.
+fn osc_string(bytes: &[u8]) -> Option<(&str, &[u8])> {
+    let end = bytes.iter().position(|&b| b == 0)?;
+    let padded = (end + 4) & !3;
+    let text = std::str::from_utf8(&bytes[..end]).ok()?;
+    Some((text, bytes.get(padded..)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode an OSC message with float arguments.
+    fn osc(address: &str, values: &[f32]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let push_string = |bytes: &mut Vec<u8>, text: &str| {
+            bytes.extend(text.as_bytes());
+            bytes.extend(vec![0; 4 - text.len() % 4]);
+        };
+        push_string(&mut bytes, address);
+        push_string(&mut bytes, &format!(",{}", "f".repeat(values.len())));
+        for value in values {
+            bytes.extend(value.to_be_bytes());
+        }
+        bytes
+    }
+
+    fn bundle(elements: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = b"#bundle\0\0\0\0\0\0\0\0\x01".to_vec();
+        for element in elements {
+            bytes.extend((element.len() as i32).to_be_bytes());
+            bytes.extend(element);
+        }
+        bytes
+    }
+
+    fn receiver(channels: usize) -> (LevelReceiver, UdpSocket) {
+        let receiver = LevelReceiver::bind("127.0.0.1:0", channels).unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.connect(receiver.local_addr().unwrap()).unwrap();
+        (receiver, sender)
+    }
+
+    /// Poll until `expected` levels arrived over loopback.
+    fn poll(receiver: &mut LevelReceiver, now: Instant, expected: usize) {
+        let mut received = 0;
+        for _ in 0..100 {
+            received += receiver.poll_at(now, MAX_DATAGRAMS_PER_POLL).unwrap();
+            if received >= expected {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        panic!("received {received} of {expected} levels");
+    }
+
+    #[test]
+    fn parses_osc_messages_and_bundles() {
+        let mut updates = Vec::new();
+        parse_osc(&osc("/meter/1", &[-12.5]), "/meter", 0, &mut updates);
+        parse_osc(&osc("/meter", &[-1.0, -2.0]), "/meter", 0, &mut updates);
+        parse_osc(&osc("/other/1", &[-3.0]), "/meter", 0, &mut updates);
+        parse_osc(&osc("/meter/1", &[-3.0, -4.0]), "/meter", 0, &mut updates);
+        let nested = bundle(&[
+            osc("/meter/3", &[-6.0]),
+            bundle(&[osc("/meter/4", &[-9.0])]),
+        ]);
+        parse_osc(&nested, "/meter", 0, &mut updates);
+        assert_eq!(
+            updates,
+            vec![(1, -12.5), (0, -1.0), (1, -2.0), (3, -6.0), (4, -9.0)]
+        );
+    }
+
+    #[test]
+    fn skips_malformed_packets() {
+        let mut updates = Vec::new();
+        let message = osc("/meter/1", &[-12.5]);
+        parse_osc(&message[..message.len() - 2], "/meter", 0, &mut updates);
+        parse_osc(b"/meter/1\0\0\0\0", "/meter", 0, &mut updates);
+        let mut bad_bundle = bundle(&[message]);
+        bad_bundle[19] = 0xFF;
+        parse_osc(&bad_bundle, "/meter", 0, &mut updates);
+        parse_text("1 -3.0 extra\nx -3\n2", &mut updates);
+        assert!(updates.is_empty());
+    }
+
+    #[test]
+    fn receives_levels_over_loopback() {
+        let (mut receiver, sender) = receiver(2);
+        sender.send(&osc("/meter/0", &[-12.0])).unwrap();
+        sender.send(&osc("/meter/0", &[-6.0])).unwrap();
+        sender.send(b"1 -20\n").unwrap();
+        sender.send(&osc("/meter/2", &[0.0])).unwrap();
+        poll(&mut receiver, Instant::now(), 3);
+        assert_eq!(receiver.db(), MeterInput::Channels(vec![-6.0, -20.0]));
+    }
+
+    #[test]
+    fn routes_and_converts_amplitudes() {
+        let (receiver, sender) = receiver(2);
+        let mut receiver = receiver
+            .prefix("/levels/")
+            .unit(LevelUnit::Amplitude)
+            .route(7, 0)
+            .route(8, 0)
+            .route(9, 1);
+        sender.send(&osc("/levels", &[1.0, 1.0])).unwrap();
+        sender.send(&osc("/levels/7", &[0.1])).unwrap();
+        sender.send(&osc("/levels/8", &[0.5])).unwrap();
+        sender.send(&osc("/levels/9", &[0.0])).unwrap();
+        poll(&mut receiver, Instant::now(), 3);
+        let db = receiver.db().into_values();
+        assert!((db[0] - MeterScale::sample_to_db(0.5)).abs() < 1e-5);
+        assert_eq!(db[1], f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn reads_a_limited_number_of_datagrams_per_poll() {
+        let (mut receiver, sender) = receiver(1);
+        for db in [-30, -20, -10] {
+            sender.send(format!("0 {db}").as_bytes()).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(receiver.poll_at(Instant::now(), 2).unwrap(), 2);
+        assert_eq!(receiver.db(), MeterInput::Channels(vec![-20.0]));
+        assert_eq!(receiver.poll_at(Instant::now(), 2).unwrap(), 1);
+        assert_eq!(receiver.db(), MeterInput::Channels(vec![-10.0]));
+    }
+
+    #[test]
+    fn channels_fall_silent_after_the_timeout() {
+        let (receiver, sender) = receiver(1);
+        let mut receiver = receiver.timeout(Duration::from_millis(100));
+        let start = Instant::now();
+        sender.send(b"0 -3").unwrap();
+        poll(&mut receiver, start, 1);
+        assert_eq!(
+            receiver.db_at(start + Duration::from_millis(100)),
+            MeterInput::Channels(vec![-3.0])
+        );
+        assert_eq!(
+            receiver.db_at(start + Duration::from_millis(101)),
+            MeterInput::Channels(vec![f32::NEG_INFINITY])
+        );
+    }
+}
@@ -1,21 +1,23 @@
-use std::{cmp::min, time::Instant};
-
 use ratatui::{
-    layout::{Constraint, Layout},
+    layout::{Alignment, Constraint, Direction, Layout},
     prelude::{symbols, BlockExt, Buffer, Color, Rect, Widget},
+    style::{Modifier, Style},
     widgets::{Paragraph, StatefulWidget},
 };
 
-use crate::meter::Meter;
+use crate::meter::{BarMode, LabelValue, Meter};
 use crate::state::MeterState;
 use crate::{
-    constants::{
-        LABEL_0, LABEL_12, LABEL_24, LABEL_3, LABEL_30, LABEL_40, LABEL_6, LABEL_60, MIN_DB,
-        RED_START, YELLOW_START,
-    },
-    scaling::MeterScale,
+    gradient::{dimmed, ColorDepth},
+    ticks::{layout_ticks, Tick},
 };
 
+/// Symbol of the minor tick marks of the scale.
+const MINOR_TICK: &str = "·";
+
+/// Width of the scale column when the meter is rendered vertically.
+pub(crate) const VERTICAL_SCALE_WIDTH: u16 = 4;
+
 impl Widget for Meter<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         Widget::render(&self, area, buf);
@@ -50,174 +52,995 @@ impl StatefulWidget for &Meter<'_> {
             return;
         }
 
-        // Prepare areas for meter(s), labels and scale if enabled
+        state.resize(self.channels);
+        let input_ratios = self.ratios();
+        let dt = state.advance();
+        let now = state.last_update;
+        let ratios = state.apply_ballistics(&input_ratios, &self.scale, dt);
+
+        let (areas, indicator_areas) = self.areas(meter_area);
+        let new_overs = self.overs_generation.0 != state.overs_generation;
+        state.overs_generation = self.overs_generation.0;
+        let color_depth = self.color_depth.unwrap_or_else(ColorDepth::detected);
+        let average_ratios = self.average_ratios();
+
+        for channel in 0..self.channels {
+            let ratio = ratios[channel];
+            let bar = Bar {
+                area: areas.meters[channel],
+                direction: self.direction,
+            };
+            let len = bar.len();
+
+            let zones = Zones::new(
+                len,
+                self.zones
+                    .iter()
+                    .map(|zone| (self.scale.db_to_ratio(zone.start_db), zone.style)),
+            );
+            let cell_style = |pos: u16| {
+                let style = zones.style(pos);
+                match self.gradient.as_ref() {
+                    Some(gradient) => {
+                        let db = self.scale.ratio_to_db((pos as f32 + 0.5) / len as f32);
+                        style.fg(gradient.color(db, color_depth))
+                    }
+                    None => style,
+                }
+            };
+
+            // --- METER BARS ---
+            // Counted in u32, as eighths of bars over 8191 cells do not fit in u16
+            let fill_eighths = (len as f32 * 8.0 * ratio).round() as u32;
+            match self.bar_mode {
+                BarMode::Cells => {
+                    let fill = (len as f32 * ratio).round() as u16;
+                    for pos in 0..len {
+                        if pos <= fill {
+                            bar.set(buf, pos, symbols::block::SEVEN_EIGHTHS, cell_style(pos));
+                        } else {
+                            bar.set(buf, pos, " ", self.empty_style);
+                        }
+                    }
+                }
+                BarMode::Eighths => {
+                    for pos in 0..len {
+                        let eighths = fill_eighths.saturating_sub(pos as u32 * 8).min(8) as u16;
+                        if eighths > 0 {
+                            let style = self.empty_style.patch(cell_style(pos));
+                            bar.set(buf, pos, bar.eighths_symbol(eighths), style);
+                        } else {
+                            bar.set(buf, pos, " ", self.empty_style);
+                        }
+                    }
+                }
+                BarMode::Segments(segments) => {
+                    for pos in 0..len {
+                        bar.set(buf, pos, " ", Style::default());
+                    }
+                    let level = len as f32 * ratio;
+                    for (start, end) in segments.layout(len) {
+                        let (symbol, style) = if level > start as f32 {
+                            (symbols::block::FULL, cell_style(start))
+                        } else if segments.dim_unlit {
+                            let style = cell_style(start);
+                            let style = match style.fg.and_then(|fg| dimmed(fg, color_depth)) {
+                                Some(fg) => style.fg(fg),
+                                None => style.add_modifier(Modifier::DIM),
+                            };
+                            (symbols::block::FULL, style)
+                        } else {
+                            (" ", self.empty_style)
+                        };
+                        for pos in start..end {
+                            bar.set(buf, pos, symbol, style);
+                        }
+                    }
+                }
+            }
+
+            // --- AVERAGE LAYER ---
+            // The bar drawn so far shows the peak level, the part beyond the average level is
+            // turned into the lighter peak layer
+            let average = average_ratios.as_ref().map(|average| average[channel]);
+            if let Some(average) = average {
+                let peak_layer = |pos: u16| self.peak_layer_style.unwrap_or(cell_style(pos));
+                let shade = symbols::shade::MEDIUM;
+                match self.bar_mode {
+                    BarMode::Cells => {
+                        let fill = (len as f32 * ratio).round() as u16;
+                        let average_fill = (len as f32 * average).round() as u16;
+                        for pos in average_fill + 1..=fill.min(len.saturating_sub(1)) {
+                            bar.set(buf, pos, shade, peak_layer(pos));
+                        }
+                    }
+                    BarMode::Eighths => {
+                        let average_eighths = (len as f32 * 8.0 * average).round() as u32;
+                        let first = average_eighths.div_ceil(8) as u16;
+                        let end = fill_eighths.div_ceil(8).min(len as u32) as u16;
+                        for pos in first..end {
+                            bar.set(buf, pos, shade, peak_layer(pos));
+                        }
+                        if !average_eighths.is_multiple_of(8) && average_eighths < fill_eighths {
+                            let pos = (average_eighths / 8) as u16;
+                            let eighths = (average_eighths % 8) as u16;
+                            let style = self.empty_style.patch(cell_style(pos));
+                            bar.set(buf, pos, bar.eighths_symbol(eighths), style);
+                        }
+                    }
+                    BarMode::Segments(segments) => {
+                        let level = len as f32 * ratio;
+                        let average_level = len as f32 * average;
+                        for (start, end) in segments.layout(len) {
+                            if level > start as f32 && average_level <= start as f32 {
+                                for pos in start..end {
+                                    bar.set(buf, pos, shade, peak_layer(start));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            // --- PEAK HOLD ---
+            state.update_peak(channel, self.scale.ratio_to_db(ratio), now, dt);
+            let peak_ratio = self.scale.db_to_ratio(state.peak_db[channel]);
+
+            // --- PEAK MARKER ---
+            match self.bar_mode {
+                BarMode::Cells => {
+                    let peak_pos =
+                        ((len as f32 * peak_ratio).round() as u16).min(len.saturating_sub(1));
+                    bar.set(
+                        buf,
+                        peak_pos,
+                        symbols::block::SEVEN_EIGHTHS,
+                        self.peak_style.unwrap_or(cell_style(peak_pos)),
+                    );
+                }
+                BarMode::Eighths => {
+                    // The marker is a partial block with its leading edge at the peak, drawn
+                    // beyond the end of the bar
+                    let peak_eighths = (len as f32 * 8.0 * peak_ratio).round() as u32;
+                    let peak_pos = peak_eighths.saturating_sub(1) / 8;
+                    let eighths = (peak_eighths - peak_pos * 8) as u16;
+                    if peak_eighths > fill_eighths && peak_pos < len as u32 {
+                        let peak_pos = peak_pos as u16;
+                        let style = self.peak_style.unwrap_or(cell_style(peak_pos));
+                        bar.set(
+                            buf,
+                            peak_pos,
+                            bar.eighths_symbol(eighths),
+                            self.empty_style.patch(style),
+                        );
+                    }
+                }
+                BarMode::Segments(segments) => {
+                    // The marker lights the segment of the peak
+                    let level = len as f32 * ratio;
+                    let peak = len as f32 * peak_ratio;
+                    let peak_segment = segments
+                        .layout(len)
+                        .into_iter()
+                        .rfind(|(start, _)| peak > *start as f32);
+                    if let Some((start, end)) = peak_segment {
+                        if level <= start as f32 {
+                            let style = self.peak_style.unwrap_or(cell_style(start));
+                            for pos in start..end {
+                                bar.set(buf, pos, symbols::block::FULL, style);
+                            }
+                        }
+                    }
+                }
+            }
+
+            // --- TRUE PEAK MARKER ---
+            let true_peak = self.true_peak.as_ref().map(|true_peak| true_peak[channel]);
+            if let Some(dbtp) = true_peak {
+                let true_peak_pos = ((len as f32 * self.scale.db_to_ratio(dbtp)).round() as u16)
+                    .min(len.saturating_sub(1));
+                let symbol = match self.direction {
+                    Direction::Horizontal => symbols::line::VERTICAL,
+                    Direction::Vertical => symbols::line::HORIZONTAL,
+                };
+                bar.set(buf, true_peak_pos, symbol, cell_style(true_peak_pos));
+            }
+
+            // --- CLIP INDICATOR ---
+            if self.input_db(channel) >= self.clip_threshold || self.overs[channel] > 0 {
+                state.clipped[channel] = true;
+            }
+            if new_overs {
+                state.over_count[channel] += self.overs[channel];
+            }
+            if let Some(indicator_area) = indicator_areas.get(channel) {
+                let indicator = Bar {
+                    area: *indicator_area,
+                    direction: self.direction,
+                };
+                let color = if state.clipped[channel] {
+                    Color::Red
+                } else {
+                    Color::DarkGray
+                };
+                indicator.set(buf, 1, symbols::block::FULL, color);
+            }
+
+            // --- DB LABEL ---
+            if let Some(db_areas) = areas.labels.as_ref() {
+                let db_area = db_areas[channel];
+                let floor_db = self.scale.floor_db;
+                let format_db = |ratio: f32, precision: usize| {
+                    let db = self.scale.ratio_to_db(ratio);
+                    if db > floor_db {
+                        format!("{db:.precision$}")
+                    } else {
+                        "-∞".to_string()
+                    }
+                };
+                let mut text = match (average, self.label_value, self.direction) {
+                    (Some(average), LabelValue::Both, Direction::Horizontal) => format!(
+                        "{} dB  AVG {} dB",
+                        format_db(ratio, 1),
+                        format_db(average, 1)
+                    ),
+                    (Some(average), LabelValue::Both, Direction::Vertical) => {
+                        format!("{}/{}", format_db(ratio, 0), format_db(average, 0))
+                    }
+                    (Some(average), LabelValue::Average, Direction::Horizontal) => {
+                        format!("{} dB", format_db(average, 1))
+                    }
+                    (Some(average), LabelValue::Average, Direction::Vertical) => {
+                        format_db(average, 1)
+                    }
+                    (_, _, Direction::Horizontal) => format!("{} dB", format_db(ratio, 1)),
+                    (_, _, Direction::Vertical) => format_db(ratio, 1),
+                };
+                if let (Some(dbtp), Direction::Horizontal) = (true_peak, self.direction) {
+                    if dbtp > floor_db {
+                        text.push_str(&format!("  TP {:.1} dBTP", dbtp));
+                    } else {
+                        text.push_str("  TP -∞ dBTP");
+                    }
+                }
+                if state.over_count[channel] > 0 && self.direction == Direction::Horizontal {
+                    text.push_str(&format!("  OVER {}", state.over_count[channel]));
+                }
+                Paragraph::new(text)
+                    .style(self.label_style)
+                    .render(db_area, buf);
+            }
+        }
+
+        // --- SCALE LABELS ---
+        if let Some(scale_area) = areas.scale {
+            self.render_meter_scale(scale_area, buf);
+        }
+    }
+}
+
+/// Areas of the different parts of a [`Meter`].
+pub(crate) struct MeterAreas {
+    pub(crate) labels: Option<Vec<Rect>>,
+    pub(crate) meters: Vec<Rect>,
+    pub(crate) scale: Option<Rect>,
+}
+
+/// A single meter bar, addressed by position along the direction it fills in.
+///
+/// Position 0 is the left end of a horizontal bar and the bottom end of a vertical bar.
+pub(crate) struct Bar {
+    pub(crate) area: Rect,
+    pub(crate) direction: Direction,
+}
+
+impl Bar {
+    /// Number of cells along the bar.
+    pub(crate) fn len(&self) -> u16 {
+        match self.direction {
+            Direction::Horizontal => self.area.width,
+            Direction::Vertical => self.area.height,
+        }
+    }
+
+    /// Partial block symbol that fills `eighths` (1 to 8) eighths of a cell from the start of the
+    /// bar.
+    pub(crate) fn eighths_symbol(&self, eighths: u16) -> &'static str {
+        match self.direction {
+            Direction::Horizontal => match eighths {
+                0 | 1 => symbols::block::ONE_EIGHTH,
+                2 => symbols::block::ONE_QUARTER,
+                3 => symbols::block::THREE_EIGHTHS,
+                4 => symbols::block::HALF,
+                5 => symbols::block::FIVE_EIGHTHS,
+                6 => symbols::block::THREE_QUARTERS,
+                7 => symbols::block::SEVEN_EIGHTHS,
+                _ => symbols::block::FULL,
+            },
+            Direction::Vertical => match eighths {
+                0 | 1 => symbols::bar::ONE_EIGHTH,
+                2 => symbols::bar::ONE_QUARTER,
+                3 => symbols::bar::THREE_EIGHTHS,
+                4 => symbols::bar::HALF,
+                5 => symbols::bar::FIVE_EIGHTHS,
+                6 => symbols::bar::THREE_QUARTERS,
+                7 => symbols::bar::SEVEN_EIGHTHS,
+                _ => symbols::bar::FULL,
+            },
+        }
+    }
+
+    /// Set every cell across the bar at `pos`.
+    pub(crate) fn set(&self, buf: &mut Buffer, pos: u16, symbol: &str, style: impl Into<Style>) {
+        if pos >= self.len() {
+            return;
+        }
+        let style = style.into();
+        match self.direction {
+            Direction::Horizontal => {
+                let x = self.area.left() + pos;
+                for y in self.area.top()..self.area.bottom() {
+                    buf[(x, y)].set_symbol(symbol).set_style(style);
+                }
+            }
+            Direction::Vertical => {
+                let y = self.area.bottom() - 1 - pos;
+                for x in self.area.left()..self.area.right() {
+                    buf[(x, y)].set_symbol(symbol).set_style(style);
+                }
+            }
+        }
+    }
+}
+
+impl MeterAreas {
+    /// Split `meter_area` into labels, bars and scale for `channels` channels.
+    pub(crate) fn new(
+        meter_area: Rect,
+        channels: usize,
+        show_labels: bool,
+        show_scale: bool,
+        direction: Direction,
+    ) -> Self {
+        match direction {
+            Direction::Horizontal => {
+                Self::horizontal(meter_area, channels, show_labels, show_scale)
+            }
+            Direction::Vertical => Self::vertical(meter_area, channels, show_labels, show_scale),
+        }
+    }
+
+    /// One row per channel, with the labels above the bars and the scale below.
+    fn horizontal(meter_area: Rect, channels: usize, show_labels: bool, show_scale: bool) -> Self {
         let mut layout_constraints = Vec::new();
-        if self.show_labels {
-            for _ in 0..self.channels {
+        if show_labels {
+            for _ in 0..channels {
                 layout_constraints.push(Constraint::Length(1));
             }
         }
-        for _ in 0..self.channels {
+        for _ in 0..channels {
             layout_constraints.push(Constraint::Length(1));
         }
-        if self.show_scale {
+        if show_scale {
             layout_constraints.push(Constraint::Length(1));
         }
         let layout_areas = Layout::vertical(layout_constraints).split(meter_area);
 
         let mut index = 0;
-        let db_areas = if self.show_labels {
-            let a = &layout_areas[index..index + self.channels];
-            index += self.channels;
+        let labels = if show_labels {
+            let a = layout_areas[index..index + channels].to_vec();
+            index += channels;
             Some(a)
         } else {
             None
         };
 
-        let meter_areas = &layout_areas[index..index + self.channels];
-        index += self.channels;
+        let meters = layout_areas[index..index + channels].to_vec();
+        index += channels;
 
-        let scale_area = if self.show_scale {
+        let scale = if show_scale {
             Some(layout_areas[index])
         } else {
             None
         };
 
-        let meter_width = meter_area.width as f32;
+        MeterAreas {
+            labels,
+            meters,
+            scale,
+        }
+    }
 
-        // Compute color zones (same for all channels)
-        // There should be at least 1 bar yellow and 1 bar red for the rightmost meter bars.
-        let end = meter_areas[0].left() + meter_width as u16;
-        let yellow_start = min(
-            meter_areas[0].left() + (meter_width * *YELLOW_START).round() as u16,
-            end - 2,
-        );
-        let red_start = min(
-            meter_areas[0].left() + (meter_width * *RED_START).round() as u16,
-            end - 1,
-        );
+    /// One column per channel, with the labels above the bars and the scale to the left.
+    fn vertical(meter_area: Rect, channels: usize, show_labels: bool, show_scale: bool) -> Self {
+        let mut layout_constraints = Vec::new();
+        if show_scale {
+            layout_constraints.push(Constraint::Length(VERTICAL_SCALE_WIDTH));
+        }
+        for _ in 0..channels {
+            layout_constraints.push(Constraint::Fill(1));
+        }
+        let columns = Layout::horizontal(layout_constraints)
+            .spacing(1)
+            .split(meter_area);
 
-        for channel in 0..self.channels {
-            let ratio = self.ratio[channel];
+        let rows = if show_labels {
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(1)])
+        } else {
+            Layout::vertical([Constraint::Length(0), Constraint::Fill(1)])
+        };
 
-            // --- METER BARS ---
-            let y = meter_areas[channel].y;
-            for x in meter_areas[channel].left()..end {
-                if x <= meter_areas[channel].left() + (meter_width * ratio).round() as u16 {
-                    buf[(x, y)]
-                        .set_symbol(symbols::block::SEVEN_EIGHTHS)
-                        .set_fg(self.get_color(x, yellow_start, red_start));
-                }
-            }
+        let mut index = 0;
+        let scale = if show_scale {
+            index += 1;
+            Some(rows.split(columns[0])[1])
+        } else {
+            None
+        };
 
-            // --- PEAK HOLD ---
-            let elapsed = state.last_peak_time[channel].elapsed();
-            if ratio > state.peak_hold_ratio[channel] {
-                state.peak_hold_ratio[channel] = ratio;
-                state.last_peak_time[channel] = Instant::now();
-            } else if elapsed.as_secs_f32() > state.peak_hold_time.as_secs_f32() {
-                state.peak_hold_ratio[channel] *=
-                    (0.99 - 0.01 * elapsed.as_secs_f32()).clamp(0.1, 0.99);
-            }
+        let mut labels = Vec::new();
+        let mut meters = Vec::new();
+        for column in &columns[index..index + channels] {
+            let [label, meter] = rows.areas(*column);
+            labels.push(label);
+            meters.push(meter);
+        }
 
-            // --- PEAK MARKER ---
-            let raw_peak_x = meter_areas[channel].left()
-                + (meter_width * state.peak_hold_ratio[channel]).round() as u16;
-            let peak_x = raw_peak_x.clamp(meter_areas[channel].left(), end - 1);
+        MeterAreas {
+            labels: show_labels.then_some(labels),
+            meters,
+            scale,
+        }
+    }
+}
 
-            buf[(peak_x, y)]
-                .set_symbol(symbols::block::SEVEN_EIGHTHS)
-                .set_fg(self.get_color(peak_x, yellow_start, red_start));
+impl Meter<'_> {
+    /// Split `meter_area` into the labels, bars and scale of the meter, and the clip indicators
+    /// at the end of the bars if they are shown.
+    pub(crate) fn areas(&self, meter_area: Rect) -> (MeterAreas, Vec<Rect>) {
+        let mut areas = MeterAreas::new(
+            meter_area,
+            self.channels,
+            self.show_labels,
+            self.show_scale,
+            self.direction,
+        );
 
-            // --- DB LABEL ---
-            if let Some(db_areas) = db_areas {
-                let db_area = db_areas[channel];
-                let db_label = MeterScale::ratio_to_db(ratio);
-                let text = if db_label > MIN_DB {
-                    format!("{:.1} dB", db_label)
-                } else {
-                    "-∞ dB".to_string()
-                };
-                Paragraph::new(text).render(db_area, buf);
+        // Reserve a gap and a cell for the clip indicator at the end of the bars and the scale
+        let mut indicator_areas = Vec::new();
+        if self.show_clip_indicator {
+            for meter in &mut areas.meters {
+                let (bar_area, indicator_area) = split_end(*meter, self.direction, 2);
+                *meter = bar_area;
+                indicator_areas.push(indicator_area);
+            }
+            if let Some(scale) = areas.scale.as_mut() {
+                *scale = split_end(*scale, self.direction, 2).0;
             }
         }
+        (areas, indicator_areas)
+    }
 
-        // --- SCALE LABELS ---
-        if let Some(scale_area) = scale_area {
-            self.render_meter_scale(scale_area, buf);
+    pub(crate) fn render_meter_scale(&self, label_area: Rect, buf: &mut Buffer) {
+        let length = match self.direction {
+            Direction::Horizontal => label_area.width,
+            Direction::Vertical => label_area.height,
+        };
+        let ticks = layout_ticks(&self.scale, length, self.direction);
+        render_ticks(&ticks, self.direction, label_area, buf, self.scale_style);
+    }
+}
+
+/// Draw the labels and minor ticks laid out for `label_area` along a bar in `direction`.
+pub(crate) fn render_ticks(
+    ticks: &[Tick],
+    direction: Direction,
+    label_area: Rect,
+    buf: &mut Buffer,
+    style: Style,
+) {
+    for tick in ticks {
+        let text = tick.text.as_deref().unwrap_or(MINOR_TICK);
+        let width = text.chars().count() as u16;
+        let (x, y) = match direction {
+            Direction::Horizontal => (label_area.x + tick.start, label_area.y),
+            // Right aligned on the row of the bar cell the tick refers to
+            Direction::Vertical => (
+                label_area.right().saturating_sub(width),
+                label_area.bottom() - 1 - tick.pos,
+            ),
+        };
+        buf.set_string(x, y, text, style);
+    }
+}
+
+/// Split `cells` cells off the end of `area` that a bar in `direction` fills towards.
+///
+/// Returns the remaining area and the split off area.
+pub(crate) fn split_end(area: Rect, direction: Direction, cells: u16) -> (Rect, Rect) {
+    match direction {
+        Direction::Horizontal => {
+            let [rest, end] =
+                Layout::horizontal([Constraint::Fill(1), Constraint::Length(cells)]).areas(area);
+            (rest, end)
+        }
+        Direction::Vertical => {
+            let [end, rest] =
+                Layout::vertical([Constraint::Length(cells), Constraint::Fill(1)]).areas(area);
+            (rest, end)
         }
     }
 }
 
-impl Meter<'_> {
-    fn render_meter_scale(&self, label_area: Rect, buf: &mut Buffer) {
-        let total_width = label_area.width;
-        if total_width > 50 {
-            // Render all labels
-            self.render_scale_label("-∞", 0.0, label_area, buf, Some(1));
-            self.render_scale_label("-60", *LABEL_60, label_area, buf, None);
-            self.render_scale_label("-40", *LABEL_40, label_area, buf, None);
-            self.render_scale_label("-24", *LABEL_24, label_area, buf, None);
-            self.render_scale_label("-12", *LABEL_12, label_area, buf, None);
-            self.render_scale_label("-6", *LABEL_6, label_area, buf, None);
-            self.render_scale_label("-3", *LABEL_3, label_area, buf, None);
-            self.render_scale_label("0", *LABEL_0, label_area, buf, None);
-        } else if total_width > 35 {
-            // Render fewer labels for medium-sized areas
-            self.render_scale_label("-∞", 0.0, label_area, buf, Some(1));
-            self.render_scale_label("-60", *LABEL_60, label_area, buf, None);
-            self.render_scale_label("-40", *LABEL_40, label_area, buf, None);
-            self.render_scale_label("-24", *LABEL_24, label_area, buf, None);
-            self.render_scale_label("-12", *LABEL_12, label_area, buf, None);
-            self.render_scale_label("-6", *LABEL_6, label_area, buf, Some(1));
-            self.render_scale_label("0", *LABEL_0, label_area, buf, None);
-        } else if total_width > 20 {
-            // Render minimal labels for small areas
-            self.render_scale_label("-∞", 0.0, label_area, buf, Some(1));
-            self.render_scale_label("-60", *LABEL_60, label_area, buf, None);
-            self.render_scale_label("-30", *LABEL_30, label_area, buf, None);
-            self.render_scale_label("-12", *LABEL_12, label_area, buf, None);
-            self.render_scale_label("0", *LABEL_0, label_area, buf, None);
-        } else {
-            // Render least labels for small areas
-            self.render_scale_label("-∞", 0.0, label_area, buf, Some(1));
-            self.render_scale_label("-60", *LABEL_60, label_area, buf, None);
-            self.render_scale_label("-30", *LABEL_30, label_area, buf, None);
-            self.render_scale_label("0", *LABEL_0, label_area, buf, None);
-        }
-    }
-
-    fn render_scale_label(
-        &self,
-        text: &str,
-        ratio: f32,
-        label_area: Rect,
-        buf: &mut Buffer,
-        offset: Option<i16>,
-    ) {
-        let offset = offset.unwrap_or(0);
-        let label_base = label_area.left() as i16 - 1 + offset;
-        let label_start = (label_area.width as f32 * ratio).round() as i16;
-        let x = (label_base + label_start) as u16;
-
-        Paragraph::new(text).render(
-            Rect {
-                x,
-                y: label_area.y,
-                width: label_area.width,
-                height: 1,
-            },
-            buf,
-        );
+/// Colour zones of a bar, resolved to bar positions.
+pub(crate) struct Zones {
+    starts: Vec<u16>,
+    styles: Vec<Style>,
+}
+
+impl Zones {
+    /// Resolve zones given as start ratio and style, sorted by start, for a bar of `len` cells.
+    ///
+    /// There should be at least 1 bar in each zone above the lowest one for the topmost meter
+    /// bars.
+    pub(crate) fn new(len: u16, zones: impl IntoIterator<Item = (f32, Style)>) -> Self {
+        let (ratios, styles): (Vec<f32>, Vec<Style>) = zones.into_iter().unzip();
+        let count = ratios.len() as u16;
+        let starts = ratios
+            .iter()
+            .enumerate()
+            .map(|(index, ratio)| {
+                let cells_above = count - index as u16;
+                ((len as f32 * ratio).round() as u16).min(len.saturating_sub(cells_above))
+            })
+            .collect();
+        Self { starts, styles }
     }
 
-    fn get_color(&self, x: u16, yellow_start: u16, red_start: u16) -> Color {
-        if x >= red_start {
-            Color::Red
-        } else if x >= yellow_start {
-            Color::Yellow
-        } else {
-            Color::Green
+    /// Green, yellow and red zones starting at 0, `yellow_start` and `red_start`.
+    pub(crate) fn traffic_light(len: u16, yellow_start: f32, red_start: f32) -> Self {
+        Self::new(
+            len,
+            [
+                (0.0, Style::from(Color::Green)),
+                (yellow_start, Style::from(Color::Yellow)),
+                (red_start, Style::from(Color::Red)),
+            ],
+        )
+    }
+
+    /// Style of the zone `pos` is in.
+    pub(crate) fn style(&self, pos: u16) -> Style {
+        let index = self
+            .starts
+            .iter()
+            .rposition(|&start| pos >= start)
+            .unwrap_or(0);
+        self.styles.get(index).copied().unwrap_or_default()
+    }
+}
+
+/// Render a scale label at `ratio` along the scale.
+///
+/// Horizontal labels start one cell before the position of `ratio`, moved by `offset`. Vertical
+/// labels are right aligned on the row of `ratio`.
+pub(crate) fn render_scale_label(
+    direction: Direction,
+    text: &str,
+    ratio: f32,
+    label_area: Rect,
+    buf: &mut Buffer,
+    offset: Option<i16>,
+    style: Style,
+) {
+    if label_area.is_empty() {
+        return;
+    }
+    match direction {
+        Direction::Horizontal => {
+            let offset = offset.unwrap_or(0);
+            let label_base = label_area.left() as i16 - 1 + offset;
+            let label_start = (label_area.width as f32 * ratio).round() as i16;
+            let x = (label_base + label_start) as u16;
+
+            Paragraph::new(text).style(style).render(
+                Rect {
+                    x,
+                    y: label_area.y,
+                    width: label_area.width,
+                    height: 1,
+                },
+                buf,
+            );
+        }
+        Direction::Vertical => {
+            // Labels are placed on the same row as the bar cell they refer to.
+            let pos = ((label_area.height as f32 * ratio).round() as u16)
+                .min(label_area.height.saturating_sub(1));
+            let y = label_area.bottom() - 1 - pos;
+
+            Paragraph::new(text)
+                .style(style)
+                .alignment(Alignment::Right)
+                .render(
+                    Rect {
+                        x: label_area.x,
+                        y,
+                        width: label_area.width,
+                        height: 1,
+                    },
+                    buf,
+                );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradient::Gradient;
+    use std::time::Duration;
+
+    use crate::meter::{ColorZone, MeterInput, Segments};
+    use crate::scaling::{DbScale, LinearDb};
+    use crate::state::Clock;
+
+    #[test]
+    fn vertical_meter_fills_from_bottom() {
+        let meter = Meter::mono()
+            .direction(Direction::Vertical)
+            .show_labels(false)
+            .show_scale(false)
+            .ratio(MeterInput::Mono(0.5));
+        let area = Rect::new(0, 0, 1, 10);
+        let mut buf = Buffer::empty(area);
+        Widget::render(meter, area, &mut buf);
+
+        assert_eq!(buf[(0, 9)].symbol(), symbols::block::SEVEN_EIGHTHS);
+        assert_eq!(buf[(0, 4)].symbol(), symbols::block::SEVEN_EIGHTHS);
+        assert_eq!(buf[(0, 3)].symbol(), " ");
+        assert_eq!(buf[(0, 0)].symbol(), " ");
+    }
+
+    #[test]
+    fn multichannel_meter_renders_every_channel() {
+        let meter = Meter::new(8)
+            .show_scale(false)
+            .db(MeterInput::Channels(vec![0.0; 8]));
+        let area = Rect::new(0, 0, 20, 16);
+        let mut buf = Buffer::empty(area);
+        let mut state = MeterState::default();
+        StatefulWidget::render(meter, area, &mut buf, &mut state);
+
+        assert_eq!(state.peak_db, vec![0.0; 8]);
+        for y in 8..16 {
+            assert_eq!(buf[(19, y)].fg, Color::Red);
+        }
+    }
+
+    #[test]
+    fn custom_zones_and_empty_style() {
+        let meter = Meter::mono()
+            .show_labels(false)
+            .show_scale(false)
+            .zones([
+                ColorZone::new(-60.0, Color::Blue),
+                ColorZone::new(-6.0, Color::Magenta),
+            ])
+            .empty_style(Style::new().bg(Color::DarkGray))
+            .ratio(MeterInput::Mono(0.5));
+        let area = Rect::new(0, 0, 20, 1);
+        let mut buf = Buffer::empty(area);
+        Widget::render(meter, area, &mut buf);
+
+        assert_eq!(buf[(0, 0)].fg, Color::Blue);
+        assert_eq!(buf[(10, 0)].fg, Color::Blue);
+        assert_eq!(buf[(11, 0)].bg, Color::DarkGray);
+        assert_eq!(buf[(19, 0)].bg, Color::DarkGray);
+    }
+
+    #[test]
+    fn gradient_colours_each_cell() {
+        let gradient =
+            Gradient::new([(-60.0, Color::Rgb(0, 0, 255)), (0.0, Color::Rgb(255, 0, 0))]);
+        let meter = Meter::mono()
+            .show_labels(false)
+            .show_scale(false)
+            .gradient(gradient.clone())
+            .color_depth(ColorDepth::TrueColor)
+            .db(MeterInput::Mono(0.0));
+        let area = Rect::new(0, 0, 20, 1);
+        let mut buf = Buffer::empty(area);
+        Widget::render(&meter, area, &mut buf);
+
+        assert_eq!(buf[(0, 0)].fg, Color::Rgb(0, 0, 255));
+        for x in [10, 19] {
+            let db = DbScale::default().ratio_to_db((x as f32 + 0.5) / 20.0);
+            assert_eq!(buf[(x, 0)].fg, gradient.color(db, ColorDepth::TrueColor));
         }
+
+        let meter = meter.color_depth(ColorDepth::Ansi);
+        Widget::render(&meter, area, &mut buf);
+        assert_eq!(buf[(19, 0)].fg, Color::LightRed);
+    }
+
+    #[test]
+    fn scale_follows_db_range() {
+        let meter = Meter::mono()
+            .show_labels(false)
+            .db_range(-60.0, 6.0)
+            .mapping(LinearDb)
+            .db(MeterInput::Mono(-27.0));
+        let area = Rect::new(0, 0, 33, 2);
+        let mut buf = Buffer::empty(area);
+        Widget::render(&meter, area, &mut buf);
+
+        let line: String = (0..33).map(|x| buf[(x, 1)].symbol()).collect();
+        assert_eq!(line, "-60  · · -40  -30  -20  -10 ·  +6");
+        assert_eq!(buf[(17, 0)].symbol(), symbols::block::SEVEN_EIGHTHS);
+        assert_eq!(buf[(18, 0)].symbol(), " ");
+    }
+
+    #[test]
+    fn eighths_fill_the_leading_edge() {
+        let meter = Meter::mono()
+            .show_labels(false)
+            .show_scale(false)
+            .bar_mode(BarMode::Eighths)
+            .ratio(MeterInput::Mono(0.5 + 3.0 / 80.0));
+        let area = Rect::new(0, 0, 10, 1);
+        let mut buf = Buffer::empty(area);
+        let mut state = MeterState::default();
+        StatefulWidget::render(&meter, area, &mut buf, &mut state);
+
+        assert_eq!(buf[(4, 0)].symbol(), symbols::block::FULL);
+        assert_eq!(buf[(5, 0)].symbol(), symbols::block::THREE_EIGHTHS);
+        assert_eq!(buf[(6, 0)].symbol(), " ");
+
+        // The held peak is drawn beyond the bar with its own leading edge
+        let meter = meter.ratio(MeterInput::Mono(0.2));
+        StatefulWidget::render(&meter, area, &mut buf, &mut state);
+        assert_eq!(buf[(1, 0)].symbol(), symbols::block::FULL);
+        assert_eq!(buf[(2, 0)].symbol(), " ");
+        assert_eq!(buf[(5, 0)].symbol(), symbols::block::THREE_EIGHTHS);
+    }
+
+    #[test]
+    fn eighths_fill_bars_longer_than_8191_cells() {
+        let meter = Meter::mono()
+            .show_labels(false)
+            .show_scale(false)
+            .bar_mode(BarMode::Eighths)
+            .ratio(MeterInput::Mono(1.0));
+        let area = Rect::new(0, 0, 10_000, 1);
+        let mut buf = Buffer::empty(area);
+        Widget::render(meter, area, &mut buf);
+
+        assert_eq!(buf[(9_999, 0)].symbol(), symbols::block::FULL);
+    }
+
+    #[test]
+    fn vertical_eighths_use_lower_blocks() {
+        let meter = Meter::mono()
+            .direction(Direction::Vertical)
+            .show_labels(false)
+            .show_scale(false)
+            .bar_mode(BarMode::Eighths)
+            .ratio(MeterInput::Mono(0.125));
+        let area = Rect::new(0, 0, 1, 4);
+        let mut buf = Buffer::empty(area);
+        Widget::render(meter, area, &mut buf);
+
+        assert_eq!(buf[(0, 3)].symbol(), symbols::bar::HALF);
+        assert_eq!(buf[(0, 2)].symbol(), " ");
+    }
+
+    #[test]
+    fn segments_are_lit_dim_or_off() {
+        let segments = Segments::with_count(5);
+        let meter = Meter::mono()
+            .show_labels(false)
+            .show_scale(false)
+            .zones([ColorZone::new(-120.0, Color::Rgb(0, 200, 0))])
+            .color_depth(ColorDepth::TrueColor)
+            .bar_mode(BarMode::Segments(segments))
+            .ratio(MeterInput::Mono(0.5));
+        let area = Rect::new(0, 0, 14, 1);
+        let mut buf = Buffer::empty(area);
+        Widget::render(&meter, area, &mut buf);
+
+        let line: String = (0..14).map(|x| buf[(x, 0)].symbol()).collect();
+        assert_eq!(line, "██ ██ ██ ██ ██");
+        assert_eq!(buf[(3, 0)].fg, Color::Rgb(0, 200, 0));
+        assert_eq!(buf[(6, 0)].fg, Color::Rgb(0, 200, 0));
+        assert_eq!(buf[(9, 0)].fg, Color::Rgb(0, 60, 0));
+
+        let meter = meter.bar_mode(BarMode::Segments(Segments {
+            dim_unlit: false,
+            ..segments
+        }));
+        let mut buf = Buffer::empty(area);
+        Widget::render(&meter, area, &mut buf);
+        let line: String = (0..14).map(|x| buf[(x, 0)].symbol()).collect();
+        assert_eq!(line, "██ ██ ██      ");
+    }
+
+    #[test]
+    fn average_is_drawn_over_the_peak_layer() {
+        let meter = Meter::mono()
+            .show_scale(false)
+            .db_range(-40.0, 0.0)
+            .mapping(LinearDb)
+            .peak_layer_style(Color::Gray)
+            .db(MeterInput::Mono(-10.0))
+            .average_db(MeterInput::Mono(-20.0));
+        let area = Rect::new(0, 0, 40, 2);
+        let mut buf = Buffer::empty(area);
+        Widget::render(&meter, area, &mut buf);
+
+        assert_eq!(buf[(20, 1)].symbol(), symbols::block::SEVEN_EIGHTHS);
+        assert_eq!(buf[(21, 1)].symbol(), symbols::shade::MEDIUM);
+        assert_eq!(buf[(21, 1)].fg, Color::Gray);
+        assert_eq!(buf[(29, 1)].symbol(), symbols::shade::MEDIUM);
+        assert_eq!(buf[(32, 1)].symbol(), " ");
+        let label: String = (0..22).map(|x| buf[(x, 0)].symbol()).collect();
+        assert_eq!(label, "-10.0 dB  AVG -20.0 dB");
+
+        let meter = meter.label_value(LabelValue::Average);
+        let mut buf = Buffer::empty(area);
+        Widget::render(&meter, area, &mut buf);
+        let label: String = (0..9).map(|x| buf[(x, 0)].symbol()).collect();
+        assert_eq!(label, "-20.0 dB ");
+    }
+
+    #[test]
+    fn peak_hold_follows_the_manual_clock() {
+        let meter = Meter::mono()
+            .show_labels(false)
+            .show_scale(false)
+            .db_range(-60.0, 0.0)
+            .mapping(LinearDb);
+        let area = Rect::new(0, 0, 60, 1);
+        let mut buf = Buffer::empty(area);
+        let mut state = MeterState::with_clock(Clock::Manual);
+        let peak_marker = |buf: &Buffer| (11..60).find(|&x| buf[(x, 0)].symbol() != " ");
+
+        StatefulWidget::render(
+            meter.clone().db(MeterInput::Mono(-6.0)),
+            area,
+            &mut buf,
+            &mut state,
+        );
+        assert_eq!(buf[(54, 0)].symbol(), symbols::block::SEVEN_EIGHTHS);
+        assert_eq!(buf[(55, 0)].symbol(), " ");
+
+        // Held for one second, then falls at 20 dB/s
+        let quiet = meter.db(MeterInput::Mono(-50.0));
+        for (dt, expected) in [(900, 54), (600, 44), (500, 34)] {
+            state.clock.update(Duration::from_millis(dt));
+            let mut buf = Buffer::empty(area);
+            StatefulWidget::render(&quiet, area, &mut buf, &mut state);
+            assert_eq!(peak_marker(&buf), Some(expected));
+        }
+    }
+
+    #[test]
+    fn true_peak_marker_and_label() {
+        let meter = Meter::mono()
+            .show_scale(false)
+            .db(MeterInput::Mono(-130.0))
+            .true_peak(MeterInput::Mono(0.4));
+        let area = Rect::new(0, 0, 30, 2);
+        let mut buf = Buffer::empty(area);
+        Widget::render(meter, area, &mut buf);
+
+        let label: String = (0..30).map(|x| buf[(x, 0)].symbol()).collect();
+        assert_eq!(label.trim_end(), "-∞ dB  TP 0.4 dBTP");
+        assert_eq!(buf[(29, 1)].symbol(), symbols::line::VERTICAL);
+        assert_eq!(buf[(29, 1)].fg, Color::Red);
+    }
+
+    #[test]
+    fn clip_indicator_latches_until_reset() {
+        let mut state = MeterState::default();
+        let area = Rect::new(0, 0, 20, 3);
+        let meter = Meter::mono().show_clip_indicator(true);
+
+        let mut buf = Buffer::empty(area);
+        StatefulWidget::render(
+            meter.clone().db(MeterInput::Mono(-20.0)),
+            area,
+            &mut buf,
+            &mut state,
+        );
+        assert_eq!(buf[(19, 1)].fg, Color::DarkGray);
+        assert_eq!(buf[(18, 1)].symbol(), " ");
+
+        StatefulWidget::render(
+            meter.clone().db(MeterInput::Mono(0.0)),
+            area,
+            &mut buf,
+            &mut state,
+        );
+        let mut buf = Buffer::empty(area);
+        StatefulWidget::render(
+            meter.clone().db(MeterInput::Mono(-20.0)),
+            area,
+            &mut buf,
+            &mut state,
+        );
+        assert_eq!(buf[(19, 1)].fg, Color::Red);
+
+        state.reset_clips();
+        let mut buf = Buffer::empty(area);
+        StatefulWidget::render(meter, area, &mut buf, &mut state);
+        assert_eq!(buf[(19, 1)].fg, Color::DarkGray);
+    }
+
+    #[test]
+    fn overs_are_counted() {
+        let mut state = MeterState::default();
+        let area = Rect::new(0, 0, 30, 4);
+        let meter = Meter::stereo()
+            .show_clip_indicator(true)
+            .clip_threshold(-0.1)
+            .db(MeterInput::Stereo(-130.0, -130.0))
+            .overs(&[2, 0]);
+        let mut buf = Buffer::empty(area);
+        StatefulWidget::render(meter.clone(), area, &mut buf, &mut state);
+        StatefulWidget::render(meter, area, &mut buf, &mut state);
+        assert_eq!(state.clipped, vec![true, false]);
+        assert_eq!(state.over_count, vec![2, 0]);
+
+        let meter = Meter::stereo().show_clip_indicator(true).overs(&[1, 3]);
+        StatefulWidget::render(meter, area, &mut buf, &mut state);
+        assert_eq!(state.over_count, vec![3, 3]);
+        let label: String = (0..30).map(|x| buf[(x, 0)].symbol()).collect();
+        assert_eq!(label.trim_end(), "-∞ dB  OVER 3");
+    }
+
+    #[test]
+    fn clip_threshold_is_compared_in_db() {
+        let area = Rect::new(0, 0, 30, 4);
+        let render = |meter: Meter| {
+            let mut state = MeterState::default();
+            let mut buf = Buffer::empty(area);
+            StatefulWidget::render(meter.show_clip_indicator(true), area, &mut buf, &mut state);
+            state.clipped[0]
+        };
+        // Saturated at the ceiling, but below the threshold
+        assert!(!render(
+            Meter::mono().clip_threshold(3.0).db(MeterInput::Mono(0.0))
+        ));
+        assert!(render(
+            Meter::mono().clip_threshold(3.0).db(MeterInput::Mono(3.5))
+        ));
+        // Silence below a threshold under the floor of the scale
+        assert!(!render(
+            Meter::mono()
+                .db_range(-60.0, 0.0)
+                .clip_threshold(-70.0)
+                .db(MeterInput::Mono(f32::NEG_INFINITY))
+        ));
+    }
+
+    #[test]
+    fn vertical_meter_scale_and_labels() {
+        let meter = Meter::stereo()
+            .direction(Direction::Vertical)
+            .db(MeterInput::Stereo(0.0, -130.0));
+        let area = Rect::new(0, 0, 14, 12);
+        let mut buf = Buffer::empty(area);
+        Widget::render(meter, area, &mut buf);
+
+        // Scale column on the left, aligned with the bars
+        assert_eq!(buf[(3, 1)].symbol(), "0");
+        assert_eq!(buf[(3, 11)].symbol(), "∞");
+        // Labels above each channel column
+        assert_eq!(buf[(5, 0)].symbol(), "0");
+        assert_eq!(buf[(10, 0)].symbol(), "-");
+        // Full scale fills the whole first column in red at the top
+        assert_eq!(buf[(5, 1)].fg, Color::Red);
+        assert_eq!(buf[(5, 11)].fg, Color::Green);
     }
 }
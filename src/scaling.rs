@@ -1,42 +1,183 @@
+use std::{fmt::Debug, sync::Arc};
+
 use crate::constants::MIN_DB;
 
-pub struct MeterScale {}
+/// A mapping between decibel values and the ratio of a meter bar that is filled.
+///
+/// Implement this trait to use a custom scale with a [`Meter`](crate::Meter). Both methods are
+/// only called with values inside the dB range of the meter, `floor_db` < `db` < `ceiling_db` and
+/// 0.0 < `ratio` < 1.0, and should be each other's inverse.
+///
+/// Two meters compare equal when their mappings have the same [`Debug`] output, so the output
+/// should include every parameter of the mapping, as the derived implementation does.
+pub trait ScaleMapping: Debug + Send + Sync {
+    /// Convert `db` to a ratio between 0.0 at `floor_db` and 1.0 at `ceiling_db`.
+    fn db_to_ratio(&self, db: f32, floor_db: f32, ceiling_db: f32) -> f32;
 
-/// A helper struct to convert between decibels and ratios for metering.
-impl MeterScale {
-    /// Determines the scale factor for the meter's logarithmic transformation.
-    /// This factor is used to increase the resolution of the meter at higher dB values.
-    const METER_LOG_SCALE_FACTOR: f32 = 2.0;
+    /// Convert a ratio between 0.0 and 1.0 back to a decibel value.
+    fn ratio_to_db(&self, ratio: f32, floor_db: f32, ceiling_db: f32) -> f32;
+}
+
+/// A scale that is linear in decibels.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LinearDb;
+
+impl ScaleMapping for LinearDb {
+    fn db_to_ratio(&self, db: f32, floor_db: f32, ceiling_db: f32) -> f32 {
+        (db - floor_db) / (ceiling_db - floor_db)
+    }
+
+    fn ratio_to_db(&self, ratio: f32, floor_db: f32, ceiling_db: f32) -> f32 {
+        floor_db + ratio * (ceiling_db - floor_db)
+    }
+}
+
+/// A scale that raises the linear decibel ratio to a power.
+///
+/// Exponents above 1.0 increase the resolution of the meter at higher dB values. The default
+/// exponent is 2.0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerLaw {
+    pub exponent: f32,
+}
+
+impl Default for PowerLaw {
+    fn default() -> Self {
+        Self { exponent: 2.0 }
+    }
+}
+
+impl ScaleMapping for PowerLaw {
+    fn db_to_ratio(&self, db: f32, floor_db: f32, ceiling_db: f32) -> f32 {
+        LinearDb
+            .db_to_ratio(db, floor_db, ceiling_db)
+            .powf(self.exponent)
+    }
 
-    /// Convert a decibel value to a ratio
-    pub fn db_to_ratio(db: f32) -> f32 {
-        if db <= MIN_DB {
+    fn ratio_to_db(&self, ratio: f32, floor_db: f32, ceiling_db: f32) -> f32 {
+        LinearDb.ratio_to_db(ratio.powf(1.0 / self.exponent), floor_db, ceiling_db)
+    }
+}
+
+/// The piecewise linear deflection of IEC 60268-18 peak programme meters.
+///
+/// The segments are laid out relative to `ceiling_db`, with the scale ending 70 dB below it. A
+/// floor above the end of the scale is moved to the start of the bar by stretching the
+/// deflection above it.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Iec60268;
+
+impl Iec60268 {
+    /// Start of each segment in dB below the ceiling, with the deflection at the start and the
+    /// deflection per dB.
+    const SEGMENTS: [(f32, f32, f32); 6] = [
+        (-20.0, 0.5, 0.025),
+        (-30.0, 0.3, 0.02),
+        (-40.0, 0.15, 0.015),
+        (-50.0, 0.075, 0.0075),
+        (-60.0, 0.025, 0.005),
+        (-70.0, 0.0, 0.0025),
+    ];
+
+    /// Deflection of the full 70 dB scale at `db` below the ceiling.
+    fn deflection(db: f32) -> f32 {
+        Self::SEGMENTS
+            .iter()
+            .find(|(start, _, _)| db >= *start)
+            .map_or(0.0, |(start, deflection, slope)| {
+                deflection + (db - start) * slope
+            })
+    }
+
+    /// Level below the ceiling of a deflection of the full 70 dB scale.
+    fn level(deflection: f32) -> f32 {
+        let (start, start_deflection, slope) = Self::SEGMENTS
+            .iter()
+            .copied()
+            .find(|(_, start_deflection, _)| deflection >= *start_deflection)
+            .unwrap_or(Self::SEGMENTS[5]);
+        start + (deflection - start_deflection) / slope
+    }
+}
+
+impl ScaleMapping for Iec60268 {
+    fn db_to_ratio(&self, db: f32, floor_db: f32, ceiling_db: f32) -> f32 {
+        let floor = Self::deflection(floor_db - ceiling_db);
+        (Self::deflection(db - ceiling_db) - floor) / (1.0 - floor)
+    }
+
+    fn ratio_to_db(&self, ratio: f32, floor_db: f32, ceiling_db: f32) -> f32 {
+        let floor = Self::deflection(floor_db - ceiling_db);
+        ceiling_db + Self::level(floor + ratio * (1.0 - floor))
+    }
+}
+
+/// The dB range and mapping of a meter.
+#[derive(Debug, Clone)]
+pub(crate) struct DbScale {
+    pub(crate) floor_db: f32,
+    pub(crate) ceiling_db: f32,
+    pub(crate) mapping: Arc<dyn ScaleMapping>,
+}
+
+impl Default for DbScale {
+    fn default() -> Self {
+        Self {
+            floor_db: MIN_DB,
+            ceiling_db: 0.0,
+            mapping: Arc::new(PowerLaw::default()),
+        }
+    }
+}
+
+impl PartialEq for DbScale {
+    fn eq(&self, other: &Self) -> bool {
+        self.floor_db == other.floor_db
+            && self.ceiling_db == other.ceiling_db
+            && (Arc::ptr_eq(&self.mapping, &other.mapping)
+                || format!("{:?}", self.mapping) == format!("{:?}", other.mapping))
+    }
+}
+
+impl DbScale {
+    /// Convert a decibel value to a ratio, saturating outside the range.
+    pub(crate) fn db_to_ratio(&self, db: f32) -> f32 {
+        if db <= self.floor_db {
             return 0.0;
         }
-        if db >= 0.0 {
+        if db >= self.ceiling_db {
             return 1.0;
         }
-
-        let db_ratio = 10_f32.powf(db / 20.0);
-        let min_db_ratio = 10_f32.powf(MIN_DB / 20.0);
-        let linear_ratio = (db_ratio.log10() - min_db_ratio.log10()) / (0.0 - min_db_ratio.log10());
-        linear_ratio.powf(Self::METER_LOG_SCALE_FACTOR)
+        self.mapping
+            .db_to_ratio(db, self.floor_db, self.ceiling_db)
+            .clamp(0.0, 1.0)
     }
 
-    /// Convert a ratio to a decibel value
-    pub fn ratio_to_db(ratio: f32) -> f32 {
-        let linear_ratio = ratio.powf(1.0 / Self::METER_LOG_SCALE_FACTOR);
-        let min_db_ratio = 10_f32.powf(MIN_DB / 20.0);
-        let db_ratio =
-            10_f32.powf(linear_ratio * (0.0 - min_db_ratio.log10()) + min_db_ratio.log10());
-        20.0 * db_ratio.log10()
+    /// Convert a ratio to a decibel value.
+    pub(crate) fn ratio_to_db(&self, ratio: f32) -> f32 {
+        if ratio <= 0.0 {
+            return self.floor_db;
+        }
+        if ratio >= 1.0 {
+            return self.ceiling_db;
+        }
+        self.mapping
+            .ratio_to_db(ratio, self.floor_db, self.ceiling_db)
+            .clamp(self.floor_db, self.ceiling_db)
     }
+}
+
+pub struct MeterScale {}
 
-    /// Convert a sample amplitude (between 0.0 and 1.0) to a decibel value.
-    #[allow(dead_code)]
+/// A helper struct to convert between decibels and ratios for metering.
+impl MeterScale {
+    /// Convert a sample amplitude to a decibel value.
+    ///
+    /// Amplitudes above 1.0 map to positive values so float overs stay visible. Levels below
+    /// -120 dBFS are raised to it.
     pub fn sample_to_db(sample_amplitude: f32) -> f32 {
         if sample_amplitude > 0.0 {
-            20.0 * sample_amplitude.log10().clamp(MIN_DB, 0.0)
+            20.0 * sample_amplitude.log10().max(MIN_DB / 20.0)
         } else {
             f32::NEG_INFINITY
         }
@@ -53,7 +194,7 @@ impl MeterScale {
 
         let l = MIN_DB / 20.0; // log10(min_db_ratio)
         let linear_ratio = (sample_amplitude.log10() - l) / -l;
-        linear_ratio.powf(Self::METER_LOG_SCALE_FACTOR)
+        linear_ratio.powf(PowerLaw::default().exponent)
     }
 }
 
@@ -65,26 +206,26 @@ mod tests {
 
     #[test]
     fn test_db_to_ratio_at_zero() {
-        let ratio = MeterScale::db_to_ratio(0.0);
+        let ratio = DbScale::default().db_to_ratio(0.0);
         assert!((ratio - 1.0).abs() < EPSILON);
     }
 
     #[test]
     fn test_db_to_ratio_at_min_db() {
-        let ratio = MeterScale::db_to_ratio(MIN_DB);
+        let ratio = DbScale::default().db_to_ratio(MIN_DB);
         assert!((ratio - 0.0).abs() < EPSILON);
     }
 
     #[test]
     fn test_db_to_ratio_below_min_db() {
-        let ratio = MeterScale::db_to_ratio(MIN_DB - 100.0);
+        let ratio = DbScale::default().db_to_ratio(MIN_DB - 100.0);
         println!("ratio: {}", ratio);
         assert!((ratio - 0.0).abs() < EPSILON);
     }
 
     #[test]
     fn test_db_to_ratio_above_max_db() {
-        let ratio = MeterScale::db_to_ratio(0.1);
+        let ratio = DbScale::default().db_to_ratio(0.1);
         println!("ratio: {}", ratio);
         assert!((ratio - 1.0).abs() < EPSILON);
     }
@@ -92,8 +233,8 @@ mod tests {
     #[test]
     fn test_ratio_to_db_inverts_db_to_ratio() {
         for db in [-120.0, -60.0, -20.0, -6.0, 0.0] {
-            let ratio = MeterScale::db_to_ratio(db);
-            let db_back = MeterScale::ratio_to_db(ratio);
+            let ratio = DbScale::default().db_to_ratio(db);
+            let db_back = DbScale::default().ratio_to_db(ratio);
             assert!(
                 (db - db_back).abs() < 1.0,
                 "db: {}, db_back: {}",
@@ -103,11 +244,83 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_linear_db_mapping() {
+        let scale = DbScale {
+            floor_db: -60.0,
+            ceiling_db: 6.0,
+            mapping: Arc::new(LinearDb),
+        };
+        assert_eq!(scale.db_to_ratio(-60.0), 0.0);
+        assert!((scale.db_to_ratio(-27.0) - 0.5).abs() < EPSILON);
+        assert_eq!(scale.db_to_ratio(6.0), 1.0);
+        assert!((scale.ratio_to_db(0.5) + 27.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_iec_60268_mapping() {
+        let scale = DbScale {
+            floor_db: -70.0,
+            ceiling_db: 0.0,
+            mapping: Arc::new(Iec60268),
+        };
+        for (db, ratio) in [(-60.0, 0.025), (-40.0, 0.15), (-20.0, 0.5), (-10.0, 0.75)] {
+            assert!((scale.db_to_ratio(db) - ratio).abs() < EPSILON);
+            assert!((scale.ratio_to_db(ratio) - db).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_iec_60268_mapping_starts_at_the_floor() {
+        let scale = DbScale {
+            floor_db: -40.0,
+            ceiling_db: 0.0,
+            mapping: Arc::new(Iec60268),
+        };
+        assert!(scale.mapping.db_to_ratio(-40.0, -40.0, 0.0).abs() < EPSILON);
+        assert!(scale.db_to_ratio(-39.9) < 0.01);
+        assert!((scale.db_to_ratio(-0.1) - 1.0).abs() < 0.01);
+        for db in [-35.0, -20.0, -10.0] {
+            assert!((scale.ratio_to_db(scale.db_to_ratio(db)) - db).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_scales_compare_mappings_by_value() {
+        let scale = |mapping: Arc<dyn ScaleMapping>| DbScale {
+            mapping,
+            ..Default::default()
+        };
+        assert_eq!(DbScale::default(), DbScale::default());
+        assert_eq!(scale(Arc::new(LinearDb)), scale(Arc::new(LinearDb)));
+        assert_ne!(scale(Arc::new(LinearDb)), scale(Arc::new(Iec60268)));
+        assert_ne!(
+            scale(Arc::new(PowerLaw { exponent: 3.0 })),
+            DbScale::default()
+        );
+    }
+
+    #[test]
+    fn test_power_law_mapping_inverts() {
+        let mapping = PowerLaw { exponent: 3.0 };
+        for db in [-50.0, -20.0, -1.0, 3.0] {
+            let ratio = mapping.db_to_ratio(db, -60.0, 6.0);
+            assert!((mapping.ratio_to_db(ratio, -60.0, 6.0) - db).abs() < 1e-3);
+        }
+    }
+
     #[test]
     fn test_sample_to_db_bounds() {
         assert_eq!(MeterScale::sample_to_db(0.0), f32::NEG_INFINITY);
         assert!((MeterScale::sample_to_db(1.0) - 0.0).abs() < EPSILON);
         assert!(MeterScale::sample_to_db(0.001) < -50.0);
+        assert!((MeterScale::sample_to_db(1e-12) - MIN_DB).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_sample_to_db_shows_float_overs() {
+        assert!((MeterScale::sample_to_db(2.0) - 6.0206).abs() < 1e-3);
+        assert!(MeterScale::sample_to_db(1.01) > 0.0);
     }
 
     #[test]
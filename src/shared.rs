@@ -0,0 +1,254 @@
+//! Sharing levels between a realtime audio thread and the UI thread.
+//!
+//! A [`SharedLevels`] handle is written by the audio thread and read by the UI thread without
+//! allocating, locking or blocking on the audio side.
+
+use std::sync::{
+    atomic::{AtomicU32, AtomicUsize, Ordering},
+    Arc,
+};
+
+use crate::{
+    analysis::{LevelAnalyzer, SampleLayout},
+    meter::MeterInput,
+    scaling::MeterScale,
+};
+
+/// A lock-free handle to the levels of a number of channels.
+///
+/// Clones of the handle share the same levels, so one clone can be moved to the audio thread and
+/// another kept by the UI. Writing a level only uses atomic operations and never allocates, locks
+/// or blocks. Levels are stored as sample amplitudes.
+///
+/// The sample peak is held until the UI reads it with [`SharedLevels::take_peak_db`], so short
+/// transients between two frames are never lost. The RMS level is the latest one written.
+///
+/// ```
+/// use std::thread;
+///
+/// use rataudio_meter::{Meter, SharedLevels};
+///
+/// let levels = SharedLevels::new(2);
+/// let audio = levels.clone();
+/// thread::spawn(move || {
+///     // In the audio callback
+///     audio.process_interleaved(&[0.5, -0.25, -0.5, 0.25]);
+/// })
+/// .join()
+/// .unwrap();
+///
+/// // In the UI thread, once per frame
+/// let meter = Meter::stereo()
+///     .db(levels.take_peak_db())
+///     .average_db(levels.rms_db())
+///     .overs(&levels.take_overs());
+/// ```
+#[derive(Debug, Clone)]
+pub struct SharedLevels {
+    inner: Arc<Levels>,
+}
+
+#[derive(Debug)]
+struct Levels {
+    peak: Box<[AtomicU32]>,
+    rms: Box<[AtomicU32]>,
+    overs: Box<[AtomicUsize]>,
+}
+
+impl SharedLevels {
+    /// Create a new handle for `channels` channels.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `channels` is zero.
+    pub fn new(channels: usize) -> Self {
+        assert!(channels > 0, "Channel count should be at least 1.");
+        let atomics = |value: u32| (0..channels).map(|_| AtomicU32::new(value)).collect();
+        Self {
+            inner: Arc::new(Levels {
+                peak: atomics(0),
+                rms: atomics(0),
+                overs: (0..channels).map(|_| AtomicUsize::new(0)).collect(),
+            }),
+        }
+    }
+
+    /// Get the number of channels of this [`SharedLevels`].
+    pub fn channels(&self) -> usize {
+        self.inner.peak.len()
+    }
+
+    /// Write the sample `peak` and `rms` amplitude of `channel`.
+    ///
+    /// The held peak only rises until it is read. Channels out of range are ignored.
+    pub fn write(&self, channel: usize, peak: f32, rms: f32) {
+        if let (Some(held), Some(latest)) =
+            (self.inner.peak.get(channel), self.inner.rms.get(channel))
+        {
+            // Non-negative floats order the same as their bit patterns
+            held.fetch_max(peak.abs().to_bits(), Ordering::Relaxed);
+            latest.store(rms.abs().to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    /// Add `count` overs to `channel`. Channels out of range are ignored.
+    pub fn add_overs(&self, channel: usize, count: usize) {
+        if let Some(overs) = self.inner.overs.get(channel) {
+            overs.fetch_add(count, Ordering::Relaxed);
+        }
+    }
+
+    /// Write the sample peak and RMS amplitude of each channel of a block of samples in the given
+    /// layout.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the length of `samples` is not a multiple of the channel count.
+    pub fn process(&self, samples: &[f32], layout: SampleLayout) {
+        let channels = self.channels();
+        assert!(
+            samples.len().is_multiple_of(channels),
+            "Sample count should be a multiple of the channel count."
+        );
+        let frames = samples.len() / channels;
+        if frames == 0 {
+            return;
+        }
+        for channel in 0..channels {
+            let mut peak = 0.0_f32;
+            let mut squares = 0.0_f64;
+            for frame in 0..frames {
+                let sample = match layout {
+                    SampleLayout::Interleaved => samples[frame * channels + channel],
+                    SampleLayout::Planar => samples[channel * frames + frame],
+                };
+                peak = peak.max(sample.abs());
+                squares += (sample * sample) as f64;
+            }
+            self.write(channel, peak, (squares / frames as f64).sqrt() as f32);
+        }
+    }
+
+    /// Write the levels of a block of interleaved samples.
+    pub fn process_interleaved(&self, samples: &[f32]) {
+        self.process(samples, SampleLayout::Interleaved);
+    }
+
+    /// Write the levels of a block of planar samples.
+    pub fn process_planar(&self, samples: &[f32]) {
+        self.process(samples, SampleLayout::Planar);
+    }
+
+    /// Write the peak, RMS level and overs of `analyzer` and reset its peak.
+    ///
+    /// Use this to share the windowed RMS level and over detection of a [`LevelAnalyzer`] running
+    /// on the audio thread.
+    pub fn publish(&self, analyzer: &mut LevelAnalyzer) {
+        for (channel, &peak) in analyzer.peak().iter().enumerate() {
+            self.write(channel, peak, analyzer.channel_rms(channel));
+        }
+        for (channel, &overs) in analyzer.overs().iter().enumerate() {
+            self.add_overs(channel, overs);
+        }
+        analyzer.reset_peak();
+    }
+
+    /// Read the sample peak per channel in dBFS since the last call and reset it, ready to be
+    /// passed to [`Meter::db`](crate::Meter::db).
+    pub fn take_peak_db(&self) -> MeterInput {
+        self.inner
+            .peak
+            .iter()
+            .map(|peak| MeterScale::sample_to_db(f32::from_bits(peak.swap(0, Ordering::Relaxed))))
+            .collect()
+    }
+
+    /// Read the latest RMS level per channel in dBFS, ready to be passed to
+    /// [`Meter::average_db`](crate::Meter::average_db).
+    pub fn rms_db(&self) -> MeterInput {
+        self.inner
+            .rms
+            .iter()
+            .map(|rms| MeterScale::sample_to_db(f32::from_bits(rms.load(Ordering::Relaxed))))
+            .collect()
+    }
+
+    /// Read the number of overs per channel since the last call and reset it, ready to be passed
+    /// to [`Meter::overs`](crate::Meter::overs).
+    pub fn take_overs(&self) -> Vec<usize> {
+        self.inner
+            .overs
+            .iter()
+            .map(|overs| overs.swap(0, Ordering::Relaxed))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn peaks_are_held_until_read() {
+        let levels = SharedLevels::new(2);
+        levels.write(0, 0.5, 0.1);
+        levels.write(0, 0.25, 0.2);
+        levels.write(1, -1.0, 0.0);
+
+        assert_eq!(
+            levels.take_peak_db(),
+            MeterInput::Channels(vec![MeterScale::sample_to_db(0.5), 0.0])
+        );
+        assert_eq!(
+            levels.take_peak_db(),
+            MeterInput::Channels(vec![f32::NEG_INFINITY; 2])
+        );
+        assert_eq!(
+            levels.rms_db(),
+            MeterInput::Channels(vec![MeterScale::sample_to_db(0.2), f32::NEG_INFINITY])
+        );
+    }
+
+    #[test]
+    fn process_writes_block_levels() {
+        let levels = SharedLevels::new(2);
+        levels.process_planar(&[0.5, -0.5, 0.0, 0.25]);
+        let peaks = levels.take_peak_db().into_values();
+        assert!((peaks[0] - MeterScale::sample_to_db(0.5)).abs() < 1e-5);
+        assert!((peaks[1] - MeterScale::sample_to_db(0.25)).abs() < 1e-5);
+        let rms = levels.rms_db().into_values();
+        assert!((rms[0] - MeterScale::sample_to_db(0.5)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn publish_moves_analyzer_levels() {
+        let mut analyzer = LevelAnalyzer::new(1, 4).over_length(1);
+        analyzer.process_interleaved(&[1.0, 0.5, 0.0, 0.0]);
+        let levels = SharedLevels::new(1);
+        levels.publish(&mut analyzer);
+
+        assert_eq!(analyzer.peak(), &[0.0]);
+        assert_eq!(levels.take_peak_db(), MeterInput::Channels(vec![0.0]));
+        assert_eq!(levels.take_overs(), vec![1]);
+        assert_eq!(levels.take_overs(), vec![0]);
+    }
+
+    #[test]
+    fn transients_between_reads_are_not_lost() {
+        let levels = SharedLevels::new(1);
+        let audio = levels.clone();
+        thread::spawn(move || {
+            for block in 0..1000 {
+                let peak = if block == 500 { 0.9 } else { 0.1 };
+                audio.write(0, peak, 0.1);
+            }
+        })
+        .join()
+        .unwrap();
+
+        let peak = levels.take_peak_db().into_values()[0];
+        assert!((peak - MeterScale::sample_to_db(0.9)).abs() < 1e-5);
+    }
+}
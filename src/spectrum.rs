@@ -0,0 +1,391 @@
+//! Spectrum analysis with a windowed FFT.
+//!
+//! A [`SpectrumAnalyzer`] keeps the most recent samples of a signal and computes its magnitude
+//! spectrum on demand, either per FFT bin or grouped into log-spaced or fractional-octave bands.
+//! The bands can be displayed with the [`SpectrumMeter`](crate::SpectrumMeter) widget.
+
+use std::f32::consts::{PI, TAU};
+
+use crate::{analysis::SampleLayout, scaling::MeterScale};
+
+/// Lowest frequency of the bands in Hz.
+const MIN_FREQUENCY: f32 = 20.0;
+/// Highest frequency of the bands in Hz, limited by the Nyquist frequency.
+const MAX_FREQUENCY: f32 = 20_000.0;
+
+/// Window function applied to the samples before the FFT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WindowFunction {
+    /// Good frequency resolution and moderate leakage.
+    #[default]
+    Hann,
+    /// 4-term Blackman-Harris: very low leakage for a wide dynamic range.
+    BlackmanHarris,
+    /// Flat-top: accurate amplitudes of sine components at the cost of resolution.
+    FlatTop,
+}
+
+impl WindowFunction {
+    /// Cosine series coefficients of the window.
+    fn coefficients(&self) -> &'static [f32] {
+        match self {
+            WindowFunction::Hann => &[0.5, 0.5],
+            WindowFunction::BlackmanHarris => &[0.35875, 0.48829, 0.14128, 0.01168],
+            WindowFunction::FlatTop => &[
+                0.215_578_95,
+                0.416_631_58,
+                0.277_263_16,
+                0.083_578_95,
+                0.006_947_368,
+            ],
+        }
+    }
+
+    /// Window of `len` samples.
+    fn samples(&self, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|n| {
+                let phase = TAU * n as f32 / len as f32;
+                self.coefficients()
+                    .iter()
+                    .enumerate()
+                    .map(|(k, a)| {
+                        let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+                        sign * a * (k as f32 * phase).cos()
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+}
+
+/// How FFT bins are grouped into bands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Banding {
+    /// The given number of bands, spaced logarithmically from 20 Hz to 20 kHz.
+    Logarithmic(usize),
+    /// 1/3-octave bands centred on 1 kHz.
+    ThirdOctave,
+    /// 1/6-octave bands centred on 1 kHz.
+    SixthOctave,
+}
+
+/// The level of one band of a spectrum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectrumBand {
+    /// Centre frequency in Hz.
+    pub frequency: f32,
+    /// Level in dBFS, where a full scale sine reads 0 dBFS.
+    pub db: f32,
+}
+
+/// Magnitude spectrum of the most recent samples of a signal.
+///
+/// Multichannel input is mixed down to mono by averaging the channels.
+#[derive(Debug, Clone)]
+pub struct SpectrumAnalyzer {
+    channels: usize,
+    sample_rate: u32,
+    window_function: WindowFunction,
+    window: Vec<f32>,
+    samples: Vec<f32>,
+    position: usize,
+}
+
+impl SpectrumAnalyzer {
+    /// Create a new analyzer for `channels` channels at `sample_rate` Hz with an FFT of
+    /// `fft_size` samples.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `channels` is zero or `fft_size` is not a power of two of at
+    /// least 4.
+    pub fn new(channels: usize, sample_rate: u32, fft_size: usize) -> Self {
+        assert!(channels > 0, "Channel count should be at least 1.");
+        assert!(
+            fft_size >= 4 && fft_size.is_power_of_two(),
+            "FFT size should be a power of two of at least 4."
+        );
+        let window_function = WindowFunction::default();
+        Self {
+            channels,
+            sample_rate,
+            window_function,
+            window: window_function.samples(fft_size),
+            samples: vec![0.0; fft_size],
+            position: 0,
+        }
+    }
+
+    /// Set the window function applied before the FFT.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn window_function(mut self, window_function: WindowFunction) -> Self {
+        self.window_function = window_function;
+        self.window = window_function.samples(self.samples.len());
+        self
+    }
+
+    /// Get the FFT size of this [`SpectrumAnalyzer`].
+    pub fn fft_size(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Add a block of samples in the given layout.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the length of `samples` is not a multiple of the channel count.
+    pub fn process(&mut self, samples: &[f32], layout: SampleLayout) {
+        assert!(
+            samples.len().is_multiple_of(self.channels),
+            "Sample count should be a multiple of the channel count."
+        );
+        let frames = samples.len() / self.channels;
+        for frame in 0..frames {
+            let sum: f32 = (0..self.channels)
+                .map(|channel| match layout {
+                    SampleLayout::Interleaved => samples[frame * self.channels + channel],
+                    SampleLayout::Planar => samples[channel * frames + frame],
+                })
+                .sum();
+            self.samples[self.position] = sum / self.channels as f32;
+            self.position = (self.position + 1) % self.samples.len();
+        }
+    }
+
+    /// Add a block of interleaved samples.
+    pub fn process_interleaved(&mut self, samples: &[f32]) {
+        self.process(samples, SampleLayout::Interleaved);
+    }
+
+    /// Add a block of planar samples.
+    pub fn process_planar(&mut self, samples: &[f32]) {
+        self.process(samples, SampleLayout::Planar);
+    }
+
+    /// Frequency in Hz of FFT bin `bin`.
+    pub fn bin_frequency(&self, bin: usize) -> f32 {
+        bin as f32 * self.sample_rate as f32 / self.samples.len() as f32
+    }
+
+    /// Amplitude of every FFT bin from DC up to the Nyquist frequency, scaled so that a full
+    /// scale sine reads 1.0.
+    pub fn magnitudes(&self) -> Vec<f32> {
+        let len = self.samples.len();
+        let mut re: Vec<f32> = (0..len)
+            .map(|n| self.samples[(self.position + n) % len] * self.window[n])
+            .collect();
+        let mut im = vec![0.0; len];
+        fft(&mut re, &mut im);
+
+        let coherent_gain: f32 = self.window.iter().sum::<f32>() / len as f32;
+        let scale = 2.0 / (len as f32 * coherent_gain);
+        (0..=len / 2)
+            .map(|k| (re[k] * re[k] + im[k] * im[k]).sqrt() * scale)
+            .collect()
+    }
+
+    /// Level in dBFS of every FFT bin from DC up to the Nyquist frequency.
+    pub fn spectrum(&self) -> Vec<SpectrumBand> {
+        self.magnitudes()
+            .into_iter()
+            .enumerate()
+            .map(|(bin, magnitude)| SpectrumBand {
+                frequency: self.bin_frequency(bin),
+                db: MeterScale::sample_to_db(magnitude),
+            })
+            .collect()
+    }
+
+    /// Levels of the bands of `banding` below the Nyquist frequency.
+    ///
+    /// The level of a band is the power sum of the bins within it, corrected for the equivalent
+    /// noise bandwidth of the window. Bands narrower than the bin spacing take the level of the
+    /// bin closest to their centre frequency.
+    pub fn bands(&self, banding: Banding) -> Vec<SpectrumBand> {
+        let magnitudes = self.magnitudes();
+        let bin_width = self.bin_frequency(1);
+        // A window spreads a tone over several bins, so summing their power over-reads by the
+        // equivalent noise bandwidth of the window in bins
+        let window_sum: f32 = self.window.iter().sum();
+        let window_power: f32 = self.window.iter().map(|w| w * w).sum();
+        let enbw = window_power * self.window.len() as f32 / (window_sum * window_sum);
+        let max_frequency = MAX_FREQUENCY.min(self.sample_rate as f32 / 2.0);
+
+        band_edges(banding, max_frequency)
+            .into_iter()
+            .map(|(low, center, high)| {
+                let first = (low / bin_width).ceil() as usize;
+                let last = ((high / bin_width).ceil() as usize).min(magnitudes.len());
+                let amplitude = if first < last {
+                    (magnitudes[first..last].iter().map(|m| m * m).sum::<f32>() / enbw).sqrt()
+                } else {
+                    let bin = ((center / bin_width).round() as usize).min(magnitudes.len() - 1);
+                    magnitudes[bin]
+                };
+                SpectrumBand {
+                    frequency: center,
+                    db: MeterScale::sample_to_db(amplitude),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Lower edge, centre and upper edge of every band of `banding` up to `max_frequency`.
+fn band_edges(banding: Banding, max_frequency: f32) -> Vec<(f32, f32, f32)> {
+    let octave_bands = |fraction: f32| {
+        let first = (fraction * (MIN_FREQUENCY / 1000.0).log2()).round() as i32;
+        let last = (fraction * (max_frequency / 1000.0).log2()).round() as i32;
+        (first..=last)
+            .map(|k| {
+                let center = 1000.0 * 2_f32.powf(k as f32 / fraction);
+                let half_width = 2_f32.powf(0.5 / fraction);
+                (
+                    center / half_width,
+                    center,
+                    (center * half_width).min(max_frequency),
+                )
+            })
+            .filter(|&(low, _, _)| low < max_frequency)
+            .collect()
+    };
+
+    match banding {
+        Banding::Logarithmic(count) => {
+            let ratio = (max_frequency / MIN_FREQUENCY).powf(1.0 / count.max(1) as f32);
+            (0..count)
+                .map(|i| {
+                    let low = MIN_FREQUENCY * ratio.powi(i as i32);
+                    (low, low * ratio.sqrt(), low * ratio)
+                })
+                .collect()
+        }
+        Banding::ThirdOctave => octave_bands(3.0),
+        Banding::SixthOctave => octave_bands(6.0),
+    }
+}
+
+/// In-place iterative radix-2 FFT. The length of `re` and `im` must be a power of two.
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let len = re.len();
+
+    // Bit reversal permutation
+    let mut j = 0;
+    for i in 1..len {
+        let mut bit = len >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut size = 2;
+    while size <= len {
+        let angle = -2.0 * PI / size as f32;
+        for start in (0..len).step_by(size) {
+            for k in 0..size / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (start + k, start + k + size / 2);
+                let tr = re[b] * cos - im[b] * sin;
+                let ti = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        size <<= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48_000;
+
+    fn sine(frequency: f32, amplitude: f32, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .map(|i| amplitude * (i as f32 * frequency / SAMPLE_RATE as f32 * TAU).sin())
+            .collect()
+    }
+
+    #[test]
+    fn fft_of_impulse_is_flat() {
+        let mut re = vec![0.0; 8];
+        let mut im = vec![0.0; 8];
+        re[0] = 1.0;
+        fft(&mut re, &mut im);
+        assert!(re.iter().all(|&r| (r - 1.0).abs() < 1e-6));
+        assert!(im.iter().all(|&i| i.abs() < 1e-6));
+    }
+
+    #[test]
+    fn flat_top_reads_sine_amplitude() {
+        let mut analyzer =
+            SpectrumAnalyzer::new(1, SAMPLE_RATE, 4096).window_function(WindowFunction::FlatTop);
+        // Between two bins, the worst case for the other windows
+        let frequency = analyzer.bin_frequency(100) + analyzer.bin_frequency(1) / 2.0;
+        analyzer.process_interleaved(&sine(frequency, 0.5, 4096));
+        let peak = analyzer
+            .spectrum()
+            .iter()
+            .fold(f32::MIN, |p, b| p.max(b.db));
+        assert!((peak + 6.02).abs() < 0.05, "peak: {peak}");
+    }
+
+    #[test]
+    fn peak_is_at_sine_frequency() {
+        for window_function in [WindowFunction::Hann, WindowFunction::BlackmanHarris] {
+            let mut analyzer =
+                SpectrumAnalyzer::new(2, SAMPLE_RATE, 2048).window_function(window_function);
+            let samples: Vec<f32> = sine(3000.0, 1.0, 2048)
+                .into_iter()
+                .flat_map(|s| [s, s])
+                .collect();
+            analyzer.process_interleaved(&samples);
+            let spectrum = analyzer.spectrum();
+            let peak = spectrum
+                .iter()
+                .max_by(|a, b| a.db.total_cmp(&b.db))
+                .unwrap();
+            assert!((peak.frequency - 3000.0).abs() < analyzer.bin_frequency(1));
+            assert!(peak.db.abs() < 1.5, "db: {}", peak.db);
+        }
+    }
+
+    #[test]
+    fn third_octave_bands() {
+        let edges = band_edges(Banding::ThirdOctave, 20_000.0);
+        assert_eq!(edges.len(), 31);
+        assert!((edges[0].1 - 19.69).abs() < 0.01);
+        assert!(edges.iter().any(|&(_, center, _)| center == 1000.0));
+        assert_eq!(band_edges(Banding::SixthOctave, 20_000.0).len(), 61);
+    }
+
+    #[test]
+    fn sine_falls_in_its_band() {
+        let mut analyzer = SpectrumAnalyzer::new(1, SAMPLE_RATE, 8192);
+        // -20 dBFS
+        analyzer.process_interleaved(&sine(1000.0, 0.1, 8192));
+        let bands = analyzer.bands(Banding::ThirdOctave);
+        let loudest = bands.iter().max_by(|a, b| a.db.total_cmp(&b.db)).unwrap();
+        assert_eq!(loudest.frequency, 1000.0);
+        assert!((loudest.db + 20.0).abs() < 0.1, "db: {}", loudest.db);
+    }
+
+    #[test]
+    fn logarithmic_bands_cover_the_audio_range() {
+        let analyzer = SpectrumAnalyzer::new(1, SAMPLE_RATE, 1024);
+        let bands = analyzer.bands(Banding::Logarithmic(10));
+        assert_eq!(bands.len(), 10);
+        assert!(bands.windows(2).all(|b| b[0].frequency < b[1].frequency));
+        assert!(bands.iter().all(|b| b.db == f32::NEG_INFINITY));
+    }
+}
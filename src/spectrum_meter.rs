@@ -0,0 +1,558 @@
+//! The [`SpectrumMeter`] widget is used to display the bands of a spectrum analyzer.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    prelude::{symbols, BlockExt, Buffer, Rect, Style, Widget},
+    widgets::{Block, Paragraph, StatefulWidget},
+};
+
+use crate::{
+    ballistics::Ballistics,
+    constants::MIN_DB,
+    meter::ColorZone,
+    rendering::{render_ticks, Bar, Zones},
+    scaling::{DbScale, LinearDb},
+    spectrum::SpectrumBand,
+    state::{Clock, Clocked, PeakHold, PeakHoldMode},
+    ticks::layout_ticks,
+};
+
+/// Width of the dB scale column.
+const SCALE_WIDTH: u16 = 4;
+
+/// Frequencies labelled below the bars, if there is room.
+const FREQUENCY_LABELS: [(f32, &str); 10] = [
+    (31.5, "31"),
+    (63.0, "63"),
+    (125.0, "125"),
+    (250.0, "250"),
+    (500.0, "500"),
+    (1000.0, "1k"),
+    (2000.0, "2k"),
+    (4000.0, "4k"),
+    (8000.0, "8k"),
+    (16000.0, "16k"),
+];
+
+/// State of the [`SpectrumMeter`] widget
+///
+/// When the spectrum is rendered as a stateful widget, the level of each band is smoothed with
+/// the [`Ballistics`] of the state and the peak of each band is held the same way as the peaks of
+/// a [`MeterState`](crate::MeterState).
+///
+/// The state consists of:
+/// - [`ballistics`](SpectrumState::ballistics): how the displayed level of each band follows the
+///   input
+/// - [`peak_hold_mode`](SpectrumState::peak_hold_mode): how the held peaks are released
+/// - [`peak_hold_time`](SpectrumState::peak_hold_time): the amount of time the peak of each band
+///   will be held
+/// - [`peak_fall_rate`](SpectrumState::peak_fall_rate): the speed in dB per second the peaks fall
+///   at after the hold time
+/// - [`level_db`](SpectrumState::level_db): the displayed level of each band
+/// - [`peak_db`](SpectrumState::peak_db): the held peak of each band
+/// - [`last_peak_time`](SpectrumState::last_peak_time): the time when the peak of each band was
+///   last updated
+/// - [`clock`](SpectrumState::clock): the source of time and the current time of the state
+/// - [`last_update`](SpectrumState::last_update): the time when the state was last updated
+#[derive(Debug, Clone)]
+pub struct SpectrumState {
+    pub ballistics: Ballistics,
+    pub peak_hold_mode: PeakHoldMode,
+    pub peak_hold_time: Duration,
+    pub peak_fall_rate: f32,
+    pub level_db: Vec<f32>,
+    pub peak_db: Vec<f32>,
+    pub last_peak_time: Vec<Instant>,
+    pub clock: Clocked,
+    pub last_update: Instant,
+}
+
+impl Default for SpectrumState {
+    fn default() -> Self {
+        let clock = Clocked::default();
+        Self {
+            ballistics: Ballistics::Instant,
+            peak_hold_mode: PeakHoldMode::default(),
+            peak_hold_time: Duration::from_secs(1),
+            peak_fall_rate: 20.0,
+            level_db: Vec::new(),
+            peak_db: Vec::new(),
+            last_peak_time: Vec::new(),
+            clock,
+            last_update: clock.now,
+        }
+    }
+}
+
+impl SpectrumState {
+    /// Create a new [`SpectrumState`] smoothing the bands with the given [`Ballistics`].
+    pub fn with_ballistics(ballistics: Ballistics) -> Self {
+        Self {
+            ballistics,
+            ..Default::default()
+        }
+    }
+
+    /// Create a new [`SpectrumState`] with the given [`Clock`].
+    pub fn with_clock(clock: Clock) -> Self {
+        let clock = Clocked::new(clock);
+        Self {
+            clock,
+            last_update: clock.now,
+            ..Default::default()
+        }
+    }
+
+    /// Smooth the `bands` and update the held peaks.
+    fn smooth(&mut self, bands: &[SpectrumBand]) {
+        let now = self.clock.read();
+        let dt = now
+            .saturating_duration_since(self.last_update)
+            .as_secs_f32();
+        self.last_update = now;
+
+        self.level_db.resize(bands.len(), MIN_DB);
+        self.peak_db.resize(bands.len(), MIN_DB);
+        self.last_peak_time.resize(bands.len(), now);
+
+        let hold = PeakHold {
+            mode: self.peak_hold_mode,
+            time: self.peak_hold_time,
+            fall_rate: self.peak_fall_rate,
+        };
+        for (index, band) in bands.iter().enumerate() {
+            let level = self.ballistics.apply(self.level_db[index], band.db, dt);
+            self.level_db[index] = level;
+            hold.update(
+                &mut self.peak_db[index],
+                &mut self.last_peak_time[index],
+                level,
+                now,
+                dt,
+            );
+        }
+    }
+}
+
+/// A widget to display a spectrum analyzer.
+///
+/// A `SpectrumMeter` renders one vertical bar per [`SpectrumBand`], as computed by
+/// [`SpectrumAnalyzer::bands`](crate::SpectrumAnalyzer::bands), on a linear dB scale. The bars
+/// use the same colour zones as the [`Meter`](crate::Meter) by default, which can be changed with
+/// [`SpectrumMeter::zones`].
+///
+/// [`SpectrumMeter`] is also a [`StatefulWidget`], which can be used with [`SpectrumState`] to
+/// smooth the bands and hold their peaks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpectrumMeter<'a> {
+    pub(crate) block: Option<Block<'a>>,
+    pub(crate) bands: Vec<SpectrumBand>,
+    pub(crate) floor_db: f32,
+    pub(crate) ceiling_db: f32,
+    pub(crate) zones: Vec<ColorZone>,
+    pub(crate) show_scale: bool,
+    pub(crate) show_peaks: bool,
+}
+
+impl<'a> SpectrumMeter<'a> {
+    /// Create a new [`SpectrumMeter`] widget displaying `bands`.
+    pub fn new(bands: Vec<SpectrumBand>) -> Self {
+        Self {
+            block: None,
+            bands,
+            floor_db: -90.0,
+            ceiling_db: 0.0,
+            zones: ColorZone::default_zones(),
+            show_scale: true,
+            show_peaks: true,
+        }
+    }
+
+    /// Surrounds the `SpectrumMeter` with a [`Block`].
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    /// Set the range of the dB scale. Defaults to -90 dB to 0 dB.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `floor_db` is not below `ceiling_db`.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn db_range(mut self, floor_db: f32, ceiling_db: f32) -> Self {
+        assert!(floor_db < ceiling_db, "Floor should be below the ceiling.");
+        self.floor_db = floor_db;
+        self.ceiling_db = ceiling_db;
+        self
+    }
+
+    /// Set the colour zones of the bars.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `zones` is empty.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn zones(mut self, zones: impl IntoIterator<Item = ColorZone>) -> Self {
+        let mut zones: Vec<ColorZone> = zones.into_iter().collect();
+        assert!(
+            !zones.is_empty(),
+            "There should be at least one color zone."
+        );
+        zones.sort_by(|a, b| a.start_db.total_cmp(&b.start_db));
+        self.zones = zones;
+        self
+    }
+
+    /// Show or hide the dB and frequency scales.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn show_scale(mut self, show: bool) -> Self {
+        self.show_scale = show;
+        self
+    }
+
+    /// Show or hide the held peaks when rendered with a [`SpectrumState`].
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn show_peaks(mut self, show: bool) -> Self {
+        self.show_peaks = show;
+        self
+    }
+
+    fn scale(&self) -> DbScale {
+        DbScale {
+            floor_db: self.floor_db,
+            ceiling_db: self.ceiling_db,
+            mapping: Arc::new(LinearDb),
+        }
+    }
+
+    fn render_db_scale(&self, scale: &DbScale, label_area: Rect, buf: &mut Buffer) {
+        let ticks = layout_ticks(scale, label_area.height, Direction::Vertical);
+        render_ticks(
+            &ticks,
+            Direction::Vertical,
+            label_area,
+            buf,
+            Style::default(),
+        );
+    }
+
+    fn render_frequency_scale(&self, columns: &[Rect], label_area: Rect, buf: &mut Buffer) {
+        let mut free_from = label_area.left();
+        for (frequency, text) in FREQUENCY_LABELS {
+            let nearest = self
+                .bands
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| {
+                    let distance = |band: &SpectrumBand| (band.frequency / frequency).ln().abs();
+                    distance(a).total_cmp(&distance(b))
+                })
+                .map(|(index, _)| index);
+            let Some(index) = nearest else {
+                return;
+            };
+            // Skip frequencies outside the analysed range
+            if (self.bands[index].frequency / frequency).log2().abs() > 0.5 {
+                continue;
+            }
+
+            let x = columns[index].left();
+            let width = text.len() as u16;
+            if x < free_from || x + width > label_area.right() {
+                continue;
+            }
+            Paragraph::new(text).render(Rect::new(x, label_area.y, width, 1), buf);
+            free_from = x + width + 1;
+        }
+    }
+}
+
+/// Split `area` into `count` columns of equal width, `spacing` cells apart.
+fn split_columns(area: Rect, count: usize, spacing: u16) -> Vec<Rect> {
+    let gaps = spacing as usize * count.saturating_sub(1);
+    let available = (area.width as usize).saturating_sub(gaps);
+    (0..count)
+        .map(|index| {
+            let start = index * available / count + index * spacing as usize;
+            let end = (index + 1) * available / count + index * spacing as usize;
+            Rect::new(
+                area.x + start as u16,
+                area.y,
+                (end - start) as u16,
+                area.height,
+            )
+        })
+        .collect()
+}
+
+impl Widget for SpectrumMeter<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        Widget::render(&self, area, buf);
+    }
+}
+
+impl Widget for &SpectrumMeter<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut state = SpectrumState::default();
+        StatefulWidget::render(self, area, buf, &mut state);
+    }
+}
+
+impl StatefulWidget for SpectrumMeter<'_> {
+    type State = SpectrumState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        StatefulWidget::render(&self, area, buf, state);
+    }
+}
+
+impl StatefulWidget for &SpectrumMeter<'_> {
+    type State = SpectrumState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        if let Some(block) = self.block.as_ref() {
+            block.render(area, buf);
+        }
+
+        let meter_area = self.block.inner_if_some(area);
+        if meter_area.is_empty() || self.bands.is_empty() {
+            return;
+        }
+        state.smooth(&self.bands);
+
+        // Prepare areas for the dB scale, the bars and the frequency scale if enabled
+        let (db_scale_area, bars_area, frequency_area) = if self.show_scale {
+            let [rows, frequency_area] =
+                Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(meter_area);
+            let [db_scale_area, bars_area] =
+                Layout::horizontal([Constraint::Length(SCALE_WIDTH), Constraint::Fill(1)])
+                    .spacing(1)
+                    .areas(rows);
+            (Some(db_scale_area), bars_area, Some(frequency_area))
+        } else {
+            (None, meter_area, None)
+        };
+
+        let count = self.bands.len();
+        let spacing = if bars_area.width as usize >= 2 * count - 1 {
+            1
+        } else {
+            0
+        };
+        // The layout solver gets slow with thousands of bands, which can not all be shown anyway
+        let columns = if count <= bars_area.width as usize {
+            Layout::horizontal(vec![Constraint::Fill(1); count])
+                .spacing(spacing)
+                .split(bars_area)
+                .to_vec()
+        } else {
+            split_columns(bars_area, count, spacing)
+        };
+
+        let scale = self.scale();
+        let len = bars_area.height;
+        let zones = Zones::new(
+            len,
+            self.zones
+                .iter()
+                .map(|zone| (scale.db_to_ratio(zone.start_db), zone.style)),
+        );
+
+        for (index, column) in columns.iter().enumerate() {
+            let bar = Bar {
+                area: *column,
+                direction: Direction::Vertical,
+            };
+
+            // --- BAND BARS ---
+            let ratio = scale.db_to_ratio(state.level_db[index]);
+            if ratio > 0.0 {
+                let fill = (len as f32 * ratio).round() as u16;
+                for pos in 0..=fill.min(len.saturating_sub(1)) {
+                    bar.set(buf, pos, symbols::block::SEVEN_EIGHTHS, zones.style(pos));
+                }
+            }
+
+            // --- PEAK MARKERS ---
+            let peak_ratio = scale.db_to_ratio(state.peak_db[index]);
+            if self.show_peaks && peak_ratio > 0.0 && len > 0 {
+                let peak_pos =
+                    ((len as f32 * peak_ratio).round() as u16).min(len.saturating_sub(1));
+                bar.set(
+                    buf,
+                    peak_pos,
+                    symbols::block::SEVEN_EIGHTHS,
+                    zones.style(peak_pos),
+                );
+            }
+        }
+
+        // --- SCALE LABELS ---
+        if let Some(db_scale_area) = db_scale_area {
+            self.render_db_scale(&scale, db_scale_area, buf);
+        }
+        if let Some(frequency_area) = frequency_area {
+            self.render_frequency_scale(&columns, frequency_area, buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::Color;
+
+    fn bands(levels: &[f32]) -> Vec<SpectrumBand> {
+        levels
+            .iter()
+            .enumerate()
+            .map(|(i, &db)| SpectrumBand {
+                frequency: 125.0 * 2_f32.powi(i as i32),
+                db,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bars_fill_from_the_bottom() {
+        let meter = SpectrumMeter::new(bands(&[-90.0, -45.0, 0.0])).show_scale(false);
+        let area = Rect::new(0, 0, 5, 10);
+        let mut buf = Buffer::empty(area);
+        Widget::render(meter, area, &mut buf);
+
+        assert!((0..10).all(|y| buf[(0, y)].symbol() == " "));
+        assert_eq!(buf[(2, 9)].fg, Color::Green);
+        assert_eq!(buf[(2, 4)].fg, Color::Green);
+        assert_eq!(buf[(2, 3)].symbol(), " ");
+        assert_eq!(buf[(4, 0)].fg, Color::Red);
+    }
+
+    #[test]
+    fn peaks_are_held() {
+        let mut state = SpectrumState::default();
+        let area = Rect::new(0, 0, 3, 10);
+        let mut buf = Buffer::empty(area);
+        let meter = SpectrumMeter::new(bands(&[0.0, -90.0])).show_scale(false);
+        StatefulWidget::render(meter, area, &mut buf, &mut state);
+
+        let mut buf = Buffer::empty(area);
+        let meter = SpectrumMeter::new(bands(&[-90.0, -90.0])).show_scale(false);
+        StatefulWidget::render(meter, area, &mut buf, &mut state);
+
+        assert_eq!(state.peak_db[0], 0.0);
+        assert_eq!(buf[(0, 0)].fg, Color::Red);
+        assert_eq!(buf[(0, 5)].symbol(), " ");
+    }
+
+    #[test]
+    fn peaks_fall_after_the_hold_time() {
+        let mut state = SpectrumState::with_clock(Clock::Manual);
+        let area = Rect::new(0, 0, 3, 10);
+        let mut buf = Buffer::empty(area);
+        StatefulWidget::render(
+            SpectrumMeter::new(bands(&[0.0])),
+            area,
+            &mut buf,
+            &mut state,
+        );
+
+        state.clock.update(Duration::from_secs(1));
+        let meter = SpectrumMeter::new(bands(&[-90.0]));
+        StatefulWidget::render(&meter, area, &mut buf, &mut state);
+        assert_eq!(state.peak_db[0], 0.0);
+
+        state.clock.update(Duration::from_millis(500));
+        StatefulWidget::render(&meter, area, &mut buf, &mut state);
+        assert!((state.peak_db[0] + 10.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn peaks_only_fall_for_the_time_after_the_hold_time() {
+        let mut state = SpectrumState::with_clock(Clock::Manual);
+        let area = Rect::new(0, 0, 3, 10);
+        let mut buf = Buffer::empty(area);
+        StatefulWidget::render(
+            SpectrumMeter::new(bands(&[0.0])),
+            area,
+            &mut buf,
+            &mut state,
+        );
+
+        state.clock.update(Duration::from_millis(1200));
+        let meter = SpectrumMeter::new(bands(&[-90.0]));
+        StatefulWidget::render(&meter, area, &mut buf, &mut state);
+        assert!((state.peak_db[0] + 4.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn peaks_use_the_peak_hold_mode() {
+        let mut state = SpectrumState {
+            peak_hold_mode: PeakHoldMode::HoldThenJump,
+            ..SpectrumState::with_clock(Clock::Manual)
+        };
+        let area = Rect::new(0, 0, 3, 10);
+        let mut buf = Buffer::empty(area);
+        StatefulWidget::render(
+            SpectrumMeter::new(bands(&[0.0])),
+            area,
+            &mut buf,
+            &mut state,
+        );
+
+        state.clock.update(Duration::from_millis(1200));
+        let meter = SpectrumMeter::new(bands(&[-30.0]));
+        StatefulWidget::render(&meter, area, &mut buf, &mut state);
+        assert_eq!(state.peak_db[0], -30.0);
+    }
+
+    #[test]
+    fn custom_zones() {
+        let meter = SpectrumMeter::new(bands(&[0.0])).show_scale(false).zones([
+            ColorZone::new(-60.0, Color::Magenta),
+            ColorZone::new(-90.0, Color::Blue),
+        ]);
+        let area = Rect::new(0, 0, 1, 10);
+        let mut buf = Buffer::empty(area);
+        Widget::render(meter, area, &mut buf);
+
+        assert_eq!(buf[(0, 9)].fg, Color::Blue);
+        assert_eq!(buf[(0, 0)].fg, Color::Magenta);
+    }
+
+    #[test]
+    fn scales() {
+        let meter = SpectrumMeter::new(bands(&[-20.0; 8]));
+        let area = Rect::new(0, 0, 21, 10);
+        let mut buf = Buffer::empty(area);
+        Widget::render(meter, area, &mut buf);
+
+        let line = |y: u16| -> String { (0..21).map(|x| buf[(x, y)].symbol()).collect() };
+        assert_eq!(line(9), "     125 500  2k  8k ");
+        assert_eq!(&line(0)[..4], "   0");
+        assert_eq!(&line(3)[..4], " -40");
+        assert_eq!(&line(8)[..4], " -90");
+    }
+
+    #[test]
+    fn peaks_in_an_area_without_bar_rows() {
+        let meter = SpectrumMeter::new(bands(&[-6.0; 4]));
+        let area = Rect::new(0, 0, 40, 1);
+        let mut buf = Buffer::empty(area);
+        Widget::render(meter, area, &mut buf);
+    }
+
+    #[test]
+    fn more_bands_than_fit_in_u16_spacing() {
+        let meter = SpectrumMeter::new(bands(&[-6.0; 40000])).show_scale(false);
+        let area = Rect::new(0, 0, 40, 4);
+        let mut buf = Buffer::empty(area);
+        Widget::render(meter, area, &mut buf);
+    }
+}
@@ -1,28 +1,396 @@
 use std::time::{Duration, Instant};
 
-/// State of the [`Meter`] widget
+use crate::{ballistics::Ballistics, constants::MIN_DB, scaling::DbScale};
+
+/// How the peak hold of a [`MeterState`] is released
+///
+/// [`Meter`]: crate::Meter
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PeakHoldMode {
+    /// Hold the peak until it is reset with [`MeterState::reset_peaks`].
+    Infinite,
+    /// Hold the peak for the hold time, then let it fall at the fall rate.
+    #[default]
+    HoldThenFall,
+    /// Hold the peak for the hold time, then jump to the current level and hold that.
+    HoldThenJump,
+}
+
+/// The source of time of a [`MeterState`]
+///
+/// The states of the other widgets that change over time, such as
+/// [`SpectrumState`](crate::SpectrumState) and [`GoniometerState`](crate::GoniometerState), use
+/// the same clock.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Clock {
+    /// Read the system clock every time the meter is rendered.
+    #[default]
+    System,
+    /// Only move time forward with [`Clocked::tick`] and [`Clocked::update`].
+    ///
+    /// Use this to test the timing of a meter exactly, or to replay a recording faster than real
+    /// time.
+    Manual,
+}
+
+/// The [`Clock`] and the current time of a widget state
+///
+/// Every state that changes over time keeps one in its `clock` field, such as
+/// [`MeterState::clock`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clocked {
+    pub source: Clock,
+    pub now: Instant,
+}
+
+impl Default for Clocked {
+    fn default() -> Self {
+        Self::new(Clock::default())
+    }
+}
+
+impl Clocked {
+    /// Create a new [`Clocked`] following `source`, starting at the current system time.
+    pub fn new(source: Clock) -> Self {
+        Self {
+            source,
+            now: Instant::now(),
+        }
+    }
+
+    /// Set the current time to `now`.
+    ///
+    /// The state moves to `now` the next time it is rendered. A [`Clock::System`] source is
+    /// switched to [`Clock::Manual`], as reading the system clock would discard the new time.
+    pub fn tick(&mut self, now: Instant) {
+        self.source = Clock::Manual;
+        self.now = now;
+    }
+
+    /// Move the current time forward by `dt`.
+    ///
+    /// Like [`Clocked::tick`], this switches a [`Clock::System`] source to [`Clock::Manual`].
+    pub fn update(&mut self, dt: Duration) {
+        self.source = Clock::Manual;
+        self.now += dt;
+    }
+
+    /// Move to the current time of the source and return it.
+    ///
+    /// [`Clock::System`] reads the system clock, [`Clock::Manual`] keeps the time as it is.
+    pub(crate) fn read(&mut self) -> Instant {
+        if self.source == Clock::System {
+            self.now = Instant::now();
+        }
+        self.now
+    }
+}
+
+/// How a held peak is released, shared by the states of the widgets that hold peaks.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PeakHold {
+    pub(crate) mode: PeakHoldMode,
+    pub(crate) time: Duration,
+    pub(crate) fall_rate: f32,
+}
+
+impl PeakHold {
+    /// Update the held `peak_db`, last raised at `last_peak_time`, with the displayed level `db`,
+    /// `dt` seconds after the previous update at `now`.
+    pub(crate) fn update(
+        self,
+        peak_db: &mut f32,
+        last_peak_time: &mut Instant,
+        db: f32,
+        now: Instant,
+        dt: f32,
+    ) {
+        if db >= *peak_db {
+            *peak_db = db;
+            *last_peak_time = now;
+            return;
+        }
+
+        let held = now.duration_since(*last_peak_time);
+        let Some(released) = held.checked_sub(self.time) else {
+            return;
+        };
+        match self.mode {
+            PeakHoldMode::Infinite => {}
+            PeakHoldMode::HoldThenFall => {
+                // Only fall for the part of the frame after the hold time ended
+                let fall_time = released.as_secs_f32().min(dt);
+                *peak_db = db.max(*peak_db - self.fall_rate * fall_time);
+            }
+            PeakHoldMode::HoldThenJump => {
+                *peak_db = db;
+                *last_peak_time = now;
+            }
+        }
+    }
+}
+
+/// State of the [`Meter`](crate::Meter) widget
 ///
 /// This state can be used to render a peak hold. When the meter is rendered as a
 /// stateful widget, it will mark the maximum peak for a certain amount of time. This will modify the [`MeterState`]
 /// object passed to the`Frame::render_stateful_widget` method.
 ///
 /// The state consists of:
-/// - [`peak_hold_ratio`]: the peak value to be displayed
-/// - [`peak_hold_time`]: the amount of time the peak value will be held
-/// - [`last_peak_time`]: the time when the peak value was last updated
+/// - [`peak_db`](MeterState::peak_db): the held peak of each channel in dB
+/// - [`peak_hold_mode`](MeterState::peak_hold_mode): how the held peak is released
+/// - [`peak_hold_time`](MeterState::peak_hold_time): the amount of time the peak value will be
+///   held
+/// - [`peak_fall_rate`](MeterState::peak_fall_rate): the speed in dB per second the peaks fall at
+///   after the hold time
+/// - [`last_peak_time`](MeterState::last_peak_time): the time when the peak value was last
+///   updated
+/// - [`ballistics`](MeterState::ballistics): how the displayed level follows the input level
+/// - [`level_db`](MeterState::level_db): the displayed level after applying the ballistics
+/// - [`clock`](MeterState::clock): the source of time and the current time of the state
+/// - [`last_update`](MeterState::last_update): the time when the state was last updated
+/// - [`clipped`](MeterState::clipped): whether the clip indicator of a channel is latched
+/// - [`over_count`](MeterState::over_count): the number of overs of a channel since the last
+///   reset
+/// - [`overs_generation`](MeterState::overs_generation): identifies the overs of the meter last
+///   added to the over counts
+///
+/// The per-channel values grow or shrink to match the number of channels of the
+/// [`Meter`](crate::Meter) it is rendered with.
+///
+/// By default the state follows the system clock. With [`Clock::Manual`] the caller moves time
+/// forward instead, and rendering only advances the state by the time passed since the last
+/// render:
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use rataudio_meter::{Clock, MeterState};
+///
+/// let mut state = MeterState::with_clock(Clock::Manual);
+/// // Render a frame, then
+/// state.clock.update(Duration::from_millis(20));
+/// // and render the next frame 20ms later.
+/// ```
 #[derive(Debug, Clone)]
 pub struct MeterState {
-    pub peak_hold_ratio: [f32; 2],
-    pub last_peak_time: [Instant; 2],
+    pub peak_db: Vec<f32>,
+    pub peak_hold_mode: PeakHoldMode,
     pub peak_hold_time: Duration,
+    pub peak_fall_rate: f32,
+    pub last_peak_time: Vec<Instant>,
+    pub ballistics: Ballistics,
+    pub level_db: Vec<f32>,
+    pub clock: Clocked,
+    pub last_update: Instant,
+    pub clipped: Vec<bool>,
+    pub over_count: Vec<usize>,
+    pub overs_generation: u64,
 }
 
 impl Default for MeterState {
     fn default() -> Self {
+        let clock = Clocked::default();
         Self {
-            peak_hold_ratio: [0.0; 2],
-            last_peak_time: [Instant::now(); 2],
+            peak_db: Vec::new(),
+            peak_hold_mode: PeakHoldMode::default(),
             peak_hold_time: Duration::from_secs(1),
+            peak_fall_rate: 20.0,
+            last_peak_time: Vec::new(),
+            ballistics: Ballistics::default(),
+            level_db: Vec::new(),
+            clock,
+            last_update: clock.now,
+            clipped: Vec::new(),
+            over_count: Vec::new(),
+            overs_generation: 0,
         }
     }
 }
+
+impl MeterState {
+    /// Create a new [`MeterState`] with the given [`Ballistics`].
+    ///
+    /// The ballistics smooth the levels given to the meter from one frame to the next. To read
+    /// tone bursts the way a standard VU meter or PPM does, apply them to the samples with
+    /// [`LevelAnalyzer::ballistics`](crate::LevelAnalyzer::ballistics) instead.
+    pub fn with_ballistics(ballistics: Ballistics) -> Self {
+        Self {
+            ballistics,
+            ..Default::default()
+        }
+    }
+
+    /// Create a new [`MeterState`] with the given [`Clock`].
+    pub fn with_clock(clock: Clock) -> Self {
+        let clock = Clocked::new(clock);
+        Self {
+            clock,
+            last_update: clock.now,
+            ..Default::default()
+        }
+    }
+
+    /// Resize the per-channel state to `channels`.
+    pub(crate) fn resize(&mut self, channels: usize) {
+        self.peak_db.resize(channels, MIN_DB);
+        self.last_peak_time.resize(channels, self.last_update);
+        self.level_db.resize(channels, MIN_DB);
+        self.clipped.resize(channels, false);
+        self.over_count.resize(channels, 0);
+    }
+
+    /// Reset the clip indicators and over counts of all channels.
+    pub fn reset_clips(&mut self) {
+        self.clipped.fill(false);
+        self.over_count.fill(0);
+    }
+
+    /// Reset the held peaks of all channels.
+    pub fn reset_peaks(&mut self) {
+        self.peak_db.fill(MIN_DB);
+    }
+
+    /// Move the state to the current time of its clock and return the seconds since the last
+    /// update.
+    pub(crate) fn advance(&mut self) -> f32 {
+        let now = self.clock.read();
+        let dt = now.saturating_duration_since(self.last_update);
+        self.last_update = now;
+        dt.as_secs_f32()
+    }
+
+    /// Update the held peak of `channel` with the displayed level `db`, `dt` seconds after the
+    /// previous update at `now`.
+    pub(crate) fn update_peak(&mut self, channel: usize, db: f32, now: Instant, dt: f32) {
+        let hold = PeakHold {
+            mode: self.peak_hold_mode,
+            time: self.peak_hold_time,
+            fall_rate: self.peak_fall_rate,
+        };
+        hold.update(
+            &mut self.peak_db[channel],
+            &mut self.last_peak_time[channel],
+            db,
+            now,
+            dt,
+        );
+    }
+
+    /// Apply the ballistics to the input `ratios` on `scale`, `dt` seconds after the previous
+    /// update, and return the ratios to display.
+    pub(crate) fn apply_ballistics(
+        &mut self,
+        ratios: &[f32],
+        scale: &DbScale,
+        dt: f32,
+    ) -> Vec<f32> {
+        if self.ballistics == Ballistics::Instant {
+            return ratios.to_vec();
+        }
+
+        ratios
+            .iter()
+            .zip(self.level_db.iter_mut())
+            .map(|(&ratio, level_db)| {
+                let target_db = scale.ratio_to_db(ratio);
+                *level_db = self.ballistics.apply(*level_db, target_db, dt);
+                scale.db_to_ratio(*level_db)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(mode: PeakHoldMode) -> (MeterState, Instant) {
+        let mut state = MeterState {
+            peak_hold_mode: mode,
+            ..Default::default()
+        };
+        state.resize(1);
+        let start = state.last_update;
+        state.update_peak(0, -6.0, start, 0.0);
+        (state, start)
+    }
+
+    #[test]
+    fn peak_is_held_for_the_hold_time() {
+        let (mut state, start) = state(PeakHoldMode::HoldThenFall);
+        state.update_peak(0, -30.0, start + Duration::from_millis(900), 0.9);
+        assert_eq!(state.peak_db[0], -6.0);
+    }
+
+    #[test]
+    fn peak_falls_in_db_per_second_after_the_hold_time() {
+        let (mut state, start) = state(PeakHoldMode::HoldThenFall);
+        // Half of the frame is past the hold time
+        state.update_peak(0, -30.0, start + Duration::from_millis(1100), 0.2);
+        assert!((state.peak_db[0] - -8.0).abs() < 1e-4);
+        state.update_peak(0, -30.0, start + Duration::from_millis(1600), 0.5);
+        assert!((state.peak_db[0] - -18.0).abs() < 1e-4);
+        state.update_peak(0, -30.0, start + Duration::from_secs(3), 1.4);
+        assert_eq!(state.peak_db[0], -30.0);
+    }
+
+    #[test]
+    fn peak_fall_does_not_depend_on_frame_rate() {
+        let (mut slow, start) = state(PeakHoldMode::HoldThenFall);
+        let (mut fast, _) = state(PeakHoldMode::HoldThenFall);
+        fast.last_peak_time = slow.last_peak_time.clone();
+        for frame in 1..=120 {
+            let now = start + Duration::from_secs_f32(2.0 * frame as f32 / 120.0);
+            fast.update_peak(0, MIN_DB, now, 1.0 / 60.0);
+            if frame % 2 == 0 {
+                slow.update_peak(0, MIN_DB, now, 1.0 / 30.0);
+            }
+        }
+        assert!((slow.peak_db[0] - fast.peak_db[0]).abs() < 1e-3);
+        assert!((fast.peak_db[0] - -26.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn infinite_hold_keeps_the_peak_until_reset() {
+        let (mut state, start) = state(PeakHoldMode::Infinite);
+        state.update_peak(0, -30.0, start + Duration::from_secs(60), 60.0);
+        assert_eq!(state.peak_db[0], -6.0);
+        state.reset_peaks();
+        state.update_peak(0, -30.0, start + Duration::from_secs(61), 1.0);
+        assert_eq!(state.peak_db[0], -30.0);
+    }
+
+    #[test]
+    fn manual_clock_only_moves_when_told() {
+        let mut state = MeterState::with_clock(Clock::Manual);
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(state.advance(), 0.0);
+        state.clock.update(Duration::from_millis(250));
+        assert_eq!(state.advance(), 0.25);
+        let now = state.clock.now + Duration::from_secs(2);
+        state.clock.tick(now);
+        assert_eq!(state.advance(), 2.0);
+        assert_eq!(state.last_update, now);
+    }
+
+    #[test]
+    fn tick_switches_a_system_clock_to_manual() {
+        let mut state = MeterState::default();
+        let now = state.last_update + Duration::from_secs(5);
+        state.clock.tick(now);
+        assert_eq!(state.clock.source, Clock::Manual);
+        assert_eq!(state.advance(), 5.0);
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(state.advance(), 0.0);
+    }
+
+    #[test]
+    fn hold_then_jump_holds_the_current_level() {
+        let (mut state, start) = state(PeakHoldMode::HoldThenJump);
+        state.update_peak(0, -30.0, start + Duration::from_millis(1100), 0.2);
+        assert_eq!(state.peak_db[0], -30.0);
+        state.update_peak(0, -40.0, start + Duration::from_millis(1500), 0.4);
+        assert_eq!(state.peak_db[0], -30.0);
+    }
+}
@@ -0,0 +1,209 @@
+//! Layout of the labels and tick marks of a decibel scale.
+
+use ratatui::layout::Direction;
+
+use crate::{constants::MIN_DB, scaling::DbScale};
+
+/// Steps of the label and minor tick values of a scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TickSteps {
+    /// Steps of the label values, in order of preference.
+    pub(crate) labels: &'static [f32],
+    /// Steps of the minor tick values, in order of preference.
+    pub(crate) minor: &'static [f32],
+}
+
+/// Steps of a dBFS scale.
+const DB_STEPS: TickSteps = TickSteps {
+    labels: &[20.0, 10.0, 6.0, 3.0],
+    minor: &[10.0, 5.0, 2.0, 1.0],
+};
+
+/// Free cells required between two labels on horizontal and on vertical scales.
+const LABEL_GAP: [u16; 2] = [2, 1];
+
+/// Free cells required around a minor tick.
+const MINOR_GAP: u16 = 1;
+
+/// A label or minor tick mark of a scale.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Tick {
+    /// Cell of the value along the scale, 0 is the start of the scale.
+    pub(crate) pos: u16,
+    /// First cell the tick covers along the scale.
+    pub(crate) start: u16,
+    /// Label text, or `None` for a minor tick mark.
+    pub(crate) text: Option<String>,
+}
+
+impl Tick {
+    /// Number of cells the tick covers along a scale in `direction`.
+    fn extent(&self, direction: Direction) -> u16 {
+        match (&self.text, direction) {
+            (Some(text), Direction::Horizontal) => text.chars().count() as u16,
+            _ => 1,
+        }
+    }
+}
+
+/// Lay out the labels and minor ticks of the dBFS `scale` on a scale of `length` cells in
+/// `direction`.
+///
+/// A floor at or below [`MIN_DB`] is labelled as minus infinity.
+pub(crate) fn layout_ticks(scale: &DbScale, length: u16, direction: Direction) -> Vec<Tick> {
+    let floor = scale.floor_db;
+    layout_ticks_with(scale, length, direction, &DB_STEPS, |db| {
+        if db == floor && floor <= MIN_DB {
+            "-∞".to_string()
+        } else {
+            format_db(db)
+        }
+    })
+}
+
+/// Lay out the labels and minor ticks of `scale` on a scale of `length` cells in `direction`,
+/// with label texts from `format`.
+///
+/// The endpoints of the range are always labelled. Other labels are picked from multiples of
+/// the label `steps` in order of preference, skipping values that would overlap a label already
+/// placed. The remaining space is filled with minor ticks.
+pub(crate) fn layout_ticks_with(
+    scale: &DbScale,
+    length: u16,
+    direction: Direction,
+    steps: &TickSteps,
+    format: impl Fn(f32) -> String,
+) -> Vec<Tick> {
+    if length == 0 {
+        return Vec::new();
+    }
+    let label_gap = match direction {
+        Direction::Horizontal => LABEL_GAP[0],
+        Direction::Vertical => LABEL_GAP[1],
+    };
+    let mut ticks: Vec<Tick> = Vec::new();
+    let place = |ticks: &mut Vec<Tick>, db: f32, text: Option<String>, gap: u16| {
+        let pos = ((length as f32 * scale.db_to_ratio(db)).round() as u16).min(length - 1);
+        let mut tick = Tick {
+            pos,
+            start: pos,
+            text,
+        };
+        let extent = tick.extent(direction);
+        if extent > length {
+            return;
+        }
+        if let (Some(text), Direction::Horizontal) = (&tick.text, direction) {
+            // Signs go before the position so the digits line up with it
+            let sign = text.starts_with(['-', '+']) as u16;
+            tick.start = pos.saturating_sub(sign).min(length - extent);
+        }
+        let overlaps = ticks.iter().any(|other| {
+            let other_end = other.start + other.extent(direction);
+            tick.start < other_end + gap && other.start < tick.start + extent + gap
+        });
+        if !overlaps {
+            ticks.push(tick);
+        }
+    };
+
+    // Endpoints
+    let ceiling = scale.ceiling_db;
+    let floor = scale.floor_db;
+    place(&mut ticks, ceiling, Some(format(ceiling)), label_gap);
+    place(&mut ticks, floor, Some(format(floor)), label_gap);
+
+    // Labels
+    for db in candidates(scale, steps.labels) {
+        place(&mut ticks, db, Some(format(db)), label_gap);
+    }
+
+    // Minor ticks
+    for db in candidates(scale, steps.minor) {
+        place(&mut ticks, db, None, MINOR_GAP);
+    }
+
+    ticks.sort_by_key(|tick| tick.pos);
+    ticks
+}
+
+/// Values strictly inside the range of `scale` that are multiples of `steps`, starting with 0 and
+/// then by step and from the top of the scale down.
+fn candidates(scale: &DbScale, steps: &[f32]) -> Vec<f32> {
+    let mut values = vec![0.0];
+    for step in steps {
+        let mut db = (scale.ceiling_db / step).floor() * step;
+        while db > scale.floor_db {
+            values.push(db);
+            db -= step;
+        }
+    }
+    values.retain(|db| *db > scale.floor_db && *db < scale.ceiling_db);
+    values
+}
+
+/// Format a scale value, with a sign above 0.
+pub(crate) fn format_db(db: f32) -> String {
+    if db > 0.0 {
+        format!("+{db:.0}")
+    } else {
+        format!("{db:.0}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::scaling::LinearDb;
+
+    fn labels(ticks: &[Tick]) -> Vec<&str> {
+        ticks
+            .iter()
+            .filter_map(|tick| tick.text.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn endpoints_are_always_labelled() {
+        let ticks = layout_ticks(&DbScale::default(), 6, Direction::Horizontal);
+        assert_eq!(labels(&ticks), vec!["-∞", "0"]);
+        assert_eq!(ticks.first().unwrap().start, 0);
+        assert_eq!(ticks.last().unwrap().start, 5);
+    }
+
+    #[test]
+    fn labels_do_not_overlap() {
+        for length in 10..120 {
+            let ticks = layout_ticks(&DbScale::default(), length, Direction::Horizontal);
+            let labels: Vec<&Tick> = ticks.iter().filter(|tick| tick.text.is_some()).collect();
+            for pair in labels.windows(2) {
+                let end = pair[0].start + pair[0].extent(Direction::Horizontal);
+                assert!(end < pair[1].start, "length {length}: {pair:?}");
+            }
+            assert!(ticks.iter().all(|tick| tick.start < length));
+        }
+    }
+
+    #[test]
+    fn labels_follow_the_range() {
+        let scale = DbScale {
+            floor_db: -60.0,
+            ceiling_db: 6.0,
+            mapping: Arc::new(LinearDb),
+        };
+        let ticks = layout_ticks(&scale, 34, Direction::Horizontal);
+        assert_eq!(
+            labels(&ticks),
+            vec!["-60", "-40", "-30", "-20", "-10", "+6"]
+        );
+        assert!(ticks.iter().any(|tick| tick.text.is_none()));
+    }
+
+    #[test]
+    fn vertical_labels_take_one_row() {
+        let ticks = layout_ticks(&DbScale::default(), 12, Direction::Vertical);
+        assert_eq!(labels(&ticks), vec!["-∞", "-60", "-40", "-20", "0"]);
+    }
+}
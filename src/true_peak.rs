@@ -0,0 +1,260 @@
+//! True-peak measurement according to ITU-R BS.1770-4 Annex 2.
+//!
+//! Sample peaks miss inter-sample peaks that appear after digital to analog conversion or
+//! sample rate conversion. A [`TruePeakDetector`] oversamples the signal with the reference
+//! polyphase FIR interpolator of BS.1770 and reports the maximum absolute value in dBTP.
+
+use crate::{analysis::SampleLayout, meter::MeterInput, scaling::MeterScale};
+
+/// Number of taps of each phase of the interpolation filter.
+const TAPS: usize = 12;
+
+/// Coefficients of the 4x oversampling interpolation filter of BS.1770-4 Annex 2, one row per
+/// phase.
+const PHASES: [[f32; TAPS]; 4] = [
+    [
+        0.001_708_984_4,
+        0.010_986_328,
+        -0.019_653_32,
+        0.033_203_125,
+        -0.059_448_242,
+        0.137_329_1,
+        0.972_167_97,
+        -0.102_294_92,
+        0.047_607_42,
+        -0.026_611_328,
+        0.014_892_578,
+        -0.008_300_781,
+    ],
+    [
+        -0.029_174_805,
+        0.029_296_875,
+        -0.051_757_813,
+        0.089_111_33,
+        -0.166_503_9,
+        0.465_087_9,
+        0.779_785_16,
+        -0.200_317_38,
+        0.101_562_5,
+        -0.058_227_54,
+        0.033_081_055,
+        -0.018_920_898,
+    ],
+    [
+        -0.018_920_898,
+        0.033_081_055,
+        -0.058_227_54,
+        0.101_562_5,
+        -0.200_317_38,
+        0.779_785_16,
+        0.465_087_9,
+        -0.166_503_9,
+        0.089_111_33,
+        -0.051_757_813,
+        0.029_296_875,
+        -0.029_174_805,
+    ],
+    [
+        -0.008_300_781,
+        0.014_892_578,
+        -0.026_611_328,
+        0.047_607_42,
+        -0.102_294_92,
+        0.972_167_97,
+        0.137_329_1,
+        -0.059_448_242,
+        0.033_203_125,
+        -0.019_653_32,
+        0.010_986_328,
+        0.001_708_984_4,
+    ],
+];
+
+/// Running per-channel true-peak level.
+///
+/// The filter of BS.1770 is specified for 48 kHz. Signals below 96 kHz are oversampled 4 times,
+/// signals below 192 kHz 2 times and signals at 192 kHz or above are already sampled densely
+/// enough to use the sample peak.
+#[derive(Debug, Clone)]
+pub struct TruePeakDetector {
+    channels: usize,
+    phases: Vec<usize>,
+    history: Vec<[f32; TAPS]>,
+    peak: Vec<f32>,
+}
+
+impl TruePeakDetector {
+    /// Create a new detector for `channels` channels at `sample_rate` Hz.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `channels` is zero.
+    pub fn new(channels: usize, sample_rate: u32) -> Self {
+        assert!(channels > 0, "Channel count should be at least 1.");
+        let phases = match sample_rate {
+            0..96_000 => vec![0, 1, 2, 3],
+            96_000..192_000 => vec![0, 2],
+            _ => Vec::new(),
+        };
+        Self {
+            channels,
+            phases,
+            history: vec![[0.0; TAPS]; channels],
+            peak: vec![0.0; channels],
+        }
+    }
+
+    /// Get the number of channels for this [`TruePeakDetector`].
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Oversampling factor used for the sample rate of this [`TruePeakDetector`].
+    pub fn oversampling(&self) -> usize {
+        self.phases.len().max(1)
+    }
+
+    /// Analyze a block of samples in the given layout.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the length of `samples` is not a multiple of the channel count.
+    pub fn process(&mut self, samples: &[f32], layout: SampleLayout) {
+        assert!(
+            samples.len().is_multiple_of(self.channels),
+            "Sample count should be a multiple of the channel count."
+        );
+        let frames = samples.len() / self.channels;
+        for frame in 0..frames {
+            for channel in 0..self.channels {
+                let sample = match layout {
+                    SampleLayout::Interleaved => samples[frame * self.channels + channel],
+                    SampleLayout::Planar => samples[channel * frames + frame],
+                };
+                self.push(channel, sample);
+            }
+        }
+    }
+
+    /// Analyze a block of interleaved samples.
+    pub fn process_interleaved(&mut self, samples: &[f32]) {
+        self.process(samples, SampleLayout::Interleaved);
+    }
+
+    /// Analyze a block of planar samples.
+    pub fn process_planar(&mut self, samples: &[f32]) {
+        self.process(samples, SampleLayout::Planar);
+    }
+
+    fn push(&mut self, channel: usize, sample: f32) {
+        let history = &mut self.history[channel];
+        history.copy_within(0..TAPS - 1, 1);
+        history[0] = sample;
+
+        let mut peak = self.peak[channel];
+        if self.phases.is_empty() {
+            peak = peak.max(sample.abs());
+        }
+        for &phase in &self.phases {
+            let value: f32 = PHASES[phase]
+                .iter()
+                .zip(history.iter())
+                .map(|(coefficient, x)| coefficient * x)
+                .sum();
+            peak = peak.max(value.abs());
+        }
+        self.peak[channel] = peak;
+    }
+
+    /// True-peak amplitude per channel since the last call to [`TruePeakDetector::reset_peak`].
+    pub fn true_peak(&self) -> &[f32] {
+        &self.peak
+    }
+
+    /// True-peak level per channel in dBTP, ready to be passed to
+    /// [`Meter::true_peak`](crate::Meter::true_peak).
+    pub fn true_peak_dbtp(&self) -> MeterInput {
+        self.peak
+            .iter()
+            .map(|&p| MeterScale::sample_to_db(p))
+            .collect()
+    }
+
+    /// Reset the held true peak of all channels.
+    pub fn reset_peak(&mut self) {
+        self.peak.fill(0.0);
+    }
+
+    /// Reset the peak and the filter state.
+    pub fn reset(&mut self) {
+        self.reset_peak();
+        self.history.fill([0.0; TAPS]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A full scale sine at a quarter of the sample rate, sampled 45 degrees off its peaks.
+    fn quarter_rate_sine(frames: usize) -> Vec<f32> {
+        (0..frames)
+            .map(|i| (i as f32 * std::f32::consts::FRAC_PI_2 + std::f32::consts::FRAC_PI_4).sin())
+            .collect()
+    }
+
+    #[test]
+    fn filter_phases_have_close_to_unity_gain() {
+        for phase in PHASES {
+            let gain: f32 = phase.iter().sum();
+            assert!((gain - 1.0).abs() < 0.03, "gain: {gain}");
+        }
+    }
+
+    #[test]
+    fn detects_inter_sample_peak() {
+        let samples = quarter_rate_sine(4800);
+        let sample_peak = samples.iter().fold(0.0_f32, |p, s| p.max(s.abs()));
+        assert!((MeterScale::sample_to_db(sample_peak) + 3.01).abs() < 0.01);
+
+        let mut detector = TruePeakDetector::new(1, 48_000);
+        detector.process_interleaved(&samples);
+        let MeterInput::Channels(dbtp) = detector.true_peak_dbtp() else {
+            panic!("Expected channel levels");
+        };
+        assert!(dbtp[0].abs() < 0.6, "dbtp: {}", dbtp[0]);
+    }
+
+    #[test]
+    fn true_peak_can_exceed_full_scale() {
+        let samples: Vec<f32> = quarter_rate_sine(4800).iter().map(|s| s * 1.4).collect();
+        let mut detector = TruePeakDetector::new(1, 44_100);
+        detector.process_interleaved(&samples);
+        assert!(detector.true_peak()[0] > 1.0);
+        let MeterInput::Channels(dbtp) = detector.true_peak_dbtp() else {
+            panic!("Expected channel levels");
+        };
+        assert!(dbtp[0] > 2.0);
+    }
+
+    #[test]
+    fn oversampling_depends_on_sample_rate() {
+        assert_eq!(TruePeakDetector::new(1, 44_100).oversampling(), 4);
+        assert_eq!(TruePeakDetector::new(1, 48_000).oversampling(), 4);
+        assert_eq!(TruePeakDetector::new(1, 96_000).oversampling(), 2);
+        assert_eq!(TruePeakDetector::new(1, 192_000).oversampling(), 1);
+    }
+
+    #[test]
+    fn peak_is_held_until_reset() {
+        let mut detector = TruePeakDetector::new(2, 48_000);
+        let mut samples = [0.0; 2 * TAPS];
+        samples[0] = 0.5;
+        samples[TAPS] = 0.25;
+        detector.process_planar(&samples);
+        assert!(detector.true_peak()[0] >= 0.48);
+        assert!(detector.true_peak()[1] >= 0.24);
+        detector.reset_peak();
+        assert_eq!(detector.true_peak(), &[0.0, 0.0]);
+    }
+}
@@ -0,0 +1,451 @@
+//! The [`Waveform`] widget is used to display the level history of each channel.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout},
+    prelude::{symbols, BlockExt, Buffer, Rect, Widget},
+    widgets::{Block, Paragraph, StatefulWidget},
+};
+
+use crate::{
+    analysis::SampleLayout,
+    constants::{RED_START_DB, YELLOW_START_DB},
+    meter::MeterInput,
+    rendering::{Bar, Zones},
+    scaling::MeterScale,
+    state::{Clock, Clocked},
+};
+
+/// How the [`Waveform`] draws each column of the history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WaveformMode {
+    /// The minimum and maximum sample around a centre line, like a waveform editor overview.
+    #[default]
+    MinMax,
+    /// The absolute peak as a bar from the bottom.
+    PeakEnvelope,
+}
+
+/// How amplitudes are mapped to the height of the [`Waveform`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WaveformScale {
+    /// The logarithmic dB mapping of the [`Meter`](crate::Meter).
+    #[default]
+    Logarithmic,
+    /// Amplitude proportional to height.
+    Linear,
+}
+
+/// State of the [`Waveform`] widget
+///
+/// The state is a ring buffer of columns per channel, each holding the minimum and maximum
+/// amplitude over `span / columns` of time. It is updated from blocks of samples with
+/// [`WaveformState::push_samples`], or from the levels given to a [`Meter`](crate::Meter) with
+/// [`WaveformState::push_db`].
+///
+/// Levels pushed with [`WaveformState::push_db`] are collected by time, which follows the
+/// [`clock`](WaveformState::clock) of the state.
+#[derive(Debug, Clone)]
+pub struct WaveformState {
+    span: Duration,
+    columns: Vec<VecDeque<(f32, f32)>>,
+    capacity: usize,
+    current: Vec<(f32, f32)>,
+    frames_in_column: usize,
+    pub clock: Clocked,
+    column_start: Instant,
+}
+
+impl WaveformState {
+    /// Create a new state for `channels` channels holding `span` of history in `columns`
+    /// columns.
+    ///
+    /// The widget shows one column per cell, so `columns` is typically the widest area the
+    /// waveform will be rendered in.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `channels`, `span` or `columns` is zero.
+    pub fn new(channels: usize, span: Duration, columns: usize) -> Self {
+        assert!(channels > 0, "Channel count should be at least 1.");
+        assert!(!span.is_zero(), "Span should not be zero.");
+        assert!(columns > 0, "Column count should be at least 1.");
+        let clock = Clocked::default();
+        Self {
+            span,
+            columns: vec![VecDeque::with_capacity(columns); channels],
+            capacity: columns,
+            current: vec![(0.0, 0.0); channels],
+            frames_in_column: 0,
+            clock,
+            column_start: clock.now,
+        }
+    }
+
+    /// Set the [`Clock`] of the state.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.clock = Clocked::new(clock);
+        self.column_start = self.clock.now;
+        self
+    }
+
+    /// Get the number of channels for this [`WaveformState`].
+    pub fn channels(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Get the amount of time the history covers.
+    pub fn span(&self) -> Duration {
+        self.span
+    }
+
+    /// Duration of a single column.
+    fn column_duration(&self) -> Duration {
+        self.span / self.capacity as u32
+    }
+
+    /// Add a block of samples at `sample_rate` in the given layout.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the length of `samples` is not a multiple of the channel count.
+    pub fn push_samples(&mut self, samples: &[f32], layout: SampleLayout, sample_rate: u32) {
+        let channels = self.channels();
+        assert!(
+            samples.len().is_multiple_of(channels),
+            "Sample count should be a multiple of the channel count."
+        );
+        let frames_per_column =
+            ((self.column_duration().as_secs_f64() * sample_rate as f64).round() as usize).max(1);
+
+        let frames = samples.len() / channels;
+        for frame in 0..frames {
+            for channel in 0..channels {
+                let sample = match layout {
+                    SampleLayout::Interleaved => samples[frame * channels + channel],
+                    SampleLayout::Planar => samples[channel * frames + frame],
+                };
+                let (min, max) = &mut self.current[channel];
+                *min = min.min(sample);
+                *max = max.max(sample);
+            }
+            self.frames_in_column += 1;
+            if self.frames_in_column >= frames_per_column {
+                self.finish_column();
+            }
+        }
+    }
+
+    /// Add levels in dBFS, such as the values given to [`Meter::db`](crate::Meter::db).
+    ///
+    /// The levels are collected into the current column until its duration has passed.
+    pub fn push_db(&mut self, input: MeterInput) {
+        let now = self.clock.read();
+        while now.saturating_duration_since(self.column_start) >= self.column_duration() {
+            self.finish_column();
+            self.column_start += self.column_duration();
+            // Do not fill the history with empty columns after a long pause
+            if now.saturating_duration_since(self.column_start) > self.span {
+                self.column_start = now;
+            }
+        }
+
+        let values = input.into_values();
+        for (channel, (min, max)) in self.current.iter_mut().enumerate() {
+            let db = values.get(channel).copied().unwrap_or(f32::NEG_INFINITY);
+            let amplitude = if db >= 0.0 {
+                1.0
+            } else {
+                10_f32.powf(db / 20.0)
+            };
+            *min = min.min(-amplitude);
+            *max = max.max(amplitude);
+        }
+    }
+
+    fn finish_column(&mut self) {
+        for (columns, current) in self.columns.iter_mut().zip(self.current.iter_mut()) {
+            if columns.len() == self.capacity {
+                columns.pop_front();
+            }
+            columns.push_back(*current);
+            *current = (0.0, 0.0);
+        }
+        self.frames_in_column = 0;
+    }
+
+    /// Remove all history.
+    pub fn clear(&mut self) {
+        for columns in &mut self.columns {
+            columns.clear();
+        }
+        self.current.fill((0.0, 0.0));
+        self.frames_in_column = 0;
+        self.column_start = self.clock.read();
+    }
+}
+
+/// A widget to display a scrolling level history.
+///
+/// A `Waveform` renders one row of the area per channel, with the newest column of the
+/// [`WaveformState`] on the right. Columns use the same colour zones as the
+/// [`Meter`](crate::Meter), so overs stay visible in red until they scroll out of the history.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Waveform<'a> {
+    pub(crate) block: Option<Block<'a>>,
+    pub(crate) mode: WaveformMode,
+    pub(crate) scale: WaveformScale,
+    pub(crate) show_scale: bool,
+}
+
+impl Default for Waveform<'_> {
+    fn default() -> Self {
+        Self {
+            block: None,
+            mode: WaveformMode::default(),
+            scale: WaveformScale::default(),
+            show_scale: true,
+        }
+    }
+}
+
+impl<'a> Waveform<'a> {
+    /// Surrounds the `Waveform` with a [`Block`].
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    /// Set how the columns of the history are drawn.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn mode(mut self, mode: WaveformMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set how amplitudes are mapped to the height of the waveform.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn scale(mut self, scale: WaveformScale) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Show or hide the time scale below the waveform.
+    #[must_use = "method moves the value of self and returns the modified value"]
+    pub fn show_scale(mut self, show: bool) -> Self {
+        self.show_scale = show;
+        self
+    }
+
+    fn amplitude_to_ratio(&self, amplitude: f32) -> f32 {
+        match self.scale {
+            WaveformScale::Logarithmic => MeterScale::sample_to_ratio(amplitude.abs()),
+            WaveformScale::Linear => amplitude.abs().min(1.0),
+        }
+    }
+
+    fn db_to_ratio(&self, db: f32) -> f32 {
+        self.amplitude_to_ratio(10_f32.powf(db / 20.0))
+    }
+
+    /// Draw one column of the history in `bar`.
+    fn render_column(&self, bar: &Bar, (min, max): (f32, f32), buf: &mut Buffer) {
+        let len = bar.len();
+        if len == 0 {
+            return;
+        }
+        match self.mode {
+            WaveformMode::PeakEnvelope => {
+                let zones = Zones::traffic_light(
+                    len,
+                    self.db_to_ratio(YELLOW_START_DB),
+                    self.db_to_ratio(RED_START_DB),
+                );
+                let ratio = self.amplitude_to_ratio(min.abs().max(max.abs()));
+                if ratio > 0.0 {
+                    let fill = (len as f32 * ratio).round() as u16;
+                    for pos in 0..=fill.min(len.saturating_sub(1)) {
+                        bar.set(buf, pos, symbols::block::FULL, zones.style(pos));
+                    }
+                }
+            }
+            WaveformMode::MinMax => {
+                // Each half of the bar covers the range from silence to full scale
+                let half = len / 2;
+                let zones = Zones::traffic_light(
+                    half,
+                    self.db_to_ratio(YELLOW_START_DB),
+                    self.db_to_ratio(RED_START_DB),
+                );
+                let below = (half as f32 * self.amplitude_to_ratio(min.min(0.0))).round() as u16;
+                let above = (half as f32 * self.amplitude_to_ratio(max.max(0.0))).round() as u16;
+                let center = len.saturating_sub(1) / 2;
+                for pos in
+                    center.saturating_sub(below)..=(center + above).min(len.saturating_sub(1))
+                {
+                    let distance = pos.abs_diff(center);
+                    bar.set(buf, pos, symbols::block::FULL, zones.style(distance));
+                }
+            }
+        }
+    }
+}
+
+impl StatefulWidget for Waveform<'_> {
+    type State = WaveformState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        StatefulWidget::render(&self, area, buf, state);
+    }
+}
+
+impl StatefulWidget for &Waveform<'_> {
+    type State = WaveformState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        if let Some(block) = self.block.as_ref() {
+            block.render(area, buf);
+        }
+
+        let waveform_area = self.block.inner_if_some(area);
+        if waveform_area.is_empty() {
+            return;
+        }
+
+        let (rows_area, scale_area) = if self.show_scale {
+            let [rows_area, scale_area] =
+                Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(waveform_area);
+            (rows_area, Some(scale_area))
+        } else {
+            (waveform_area, None)
+        };
+        let rows = Layout::vertical(vec![Constraint::Fill(1); state.channels()])
+            .spacing(1)
+            .split(rows_area);
+
+        for (row, columns) in rows.iter().zip(&state.columns) {
+            if row.height == 0 {
+                continue;
+            }
+            // The newest column is drawn at the right edge
+            let visible = columns.len().min(row.width as usize);
+            let first_x = row.right() - visible as u16;
+            for (offset, &column) in columns.iter().skip(columns.len() - visible).enumerate() {
+                let bar = Bar {
+                    area: Rect::new(first_x + offset as u16, row.y, 1, row.height),
+                    direction: Direction::Vertical,
+                };
+                self.render_column(&bar, column, buf);
+            }
+        }
+
+        // --- TIME SCALE ---
+        if let Some(scale_area) = scale_area {
+            let shown = state.span.as_secs_f32() * scale_area.width as f32 / state.capacity as f32;
+            let start = format!("-{:.1}s", shown.min(state.span.as_secs_f32()));
+            Paragraph::new(start).render(scale_area, buf);
+            Paragraph::new("now")
+                .alignment(Alignment::Right)
+                .render(scale_area, buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::Color;
+
+    fn render(waveform: Waveform, state: &mut WaveformState, area: Rect) -> Buffer {
+        let mut buf = Buffer::empty(area);
+        StatefulWidget::render(waveform.show_scale(false), area, &mut buf, state);
+        buf
+    }
+
+    #[test]
+    fn samples_are_collected_into_columns() {
+        let mut state = WaveformState::new(1, Duration::from_secs(1), 10);
+        // 100 samples per column at 1 kHz
+        let samples: Vec<f32> = (0..250)
+            .map(|i| if i == 150 { -0.5 } else { 0.1 })
+            .collect();
+        state.push_samples(&samples, SampleLayout::Interleaved, 1000);
+        assert_eq!(
+            state.columns[0].iter().copied().collect::<Vec<_>>(),
+            vec![(0.0, 0.1), (-0.5, 0.1)]
+        );
+    }
+
+    #[test]
+    fn history_is_limited_to_the_span() {
+        let mut state = WaveformState::new(2, Duration::from_secs(1), 4);
+        state.push_samples(&[0.5; 2 * 1000], SampleLayout::Interleaved, 500);
+        assert_eq!(state.columns[0].len(), 4);
+        assert_eq!(state.columns[1].len(), 4);
+    }
+
+    #[test]
+    fn newest_column_is_on_the_right() {
+        let mut state = WaveformState::new(1, Duration::from_secs(1), 10);
+        let mut samples = vec![0.0; 300];
+        samples[250] = 1.0;
+        state.push_samples(&samples, SampleLayout::Interleaved, 1000);
+
+        let area = Rect::new(0, 0, 5, 4);
+        let buf = render(
+            Waveform::default().mode(WaveformMode::PeakEnvelope),
+            &mut state,
+            area,
+        );
+        assert_eq!(buf[(4, 0)].fg, Color::Red);
+        assert_eq!(buf[(4, 3)].symbol(), symbols::block::FULL);
+        assert_eq!(buf[(3, 0)].symbol(), " ");
+        assert_eq!(buf[(1, 3)].symbol(), " ");
+    }
+
+    #[test]
+    fn min_max_is_drawn_around_the_center() {
+        let mut state = WaveformState::new(1, Duration::from_secs(1), 10);
+        state.push_samples(&[1.0; 100], SampleLayout::Interleaved, 1000);
+
+        let area = Rect::new(0, 0, 1, 5);
+        let buf = render(
+            Waveform::default().scale(WaveformScale::Linear),
+            &mut state,
+            area,
+        );
+        assert_eq!(buf[(0, 0)].fg, Color::Red);
+        assert_eq!(buf[(0, 2)].symbol(), symbols::block::FULL);
+        assert_eq!(buf[(0, 4)].symbol(), " ");
+    }
+
+    #[test]
+    fn rows_without_height_are_skipped() {
+        let mut state = WaveformState::new(4, Duration::from_secs(1), 10);
+        state.push_samples(&[0.5; 4 * 100], SampleLayout::Interleaved, 1000);
+
+        let area = Rect::new(0, 0, 40, 5);
+        let buf = render(Waveform::default(), &mut state, area);
+        assert_eq!(buf[(39, 0)].symbol(), symbols::block::FULL);
+    }
+
+    #[test]
+    fn levels_are_collected_over_time() {
+        let mut state = WaveformState::new(2, Duration::from_millis(40), 4).clock(Clock::Manual);
+        state.push_db(MeterInput::Stereo(0.0, -6.0));
+        state.clock.update(Duration::from_millis(9));
+        state.push_db(MeterInput::Stereo(-120.0, -120.0));
+        assert!(state.columns[0].is_empty());
+        state.clock.update(Duration::from_millis(1));
+        state.push_db(MeterInput::Stereo(-120.0, -120.0));
+        assert_eq!(state.columns[0].front(), Some(&(-1.0, 1.0)));
+        let (_, max) = state.columns[1][0];
+        assert!((max - 0.501).abs() < 0.01);
+    }
+}